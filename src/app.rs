@@ -0,0 +1,269 @@
+use crate::arrow::ArrowMessage;
+use crate::backend::DisplayBackend;
+use crate::error::Error;
+use crate::frame::Frame;
+use crate::panel::Orientation;
+use crate::scene::Scene;
+use crate::state::{self, SceneState};
+use std::path::PathBuf;
+use std::sync::{mpsc::Receiver, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Both layers of the frame as last sent to the panel
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shown {
+    pub black: Vec<u8>,
+    pub red: Vec<u8>,
+}
+
+impl Shown {
+    fn of(frame: &Frame) -> Self {
+        Self {
+            black: frame.black().to_vec(),
+            red: frame.red().to_vec(),
+        }
+    }
+}
+
+/// Told about the arrows after each refresh, see `App::on_refresh`
+type Listener = Box<dyn FnMut(&Scene) + Send>;
+
+/// Ties the arrows to a display backend, redrawing as messages come in
+pub struct App<B> {
+    backend: B,
+    frame: Frame,
+    scene: Arc<Mutex<Scene>>,
+    settle: Duration,
+    state_file: Option<PathBuf>,
+    frame_file: Option<PathBuf>,
+    shown: Arc<Mutex<Option<Shown>>>,
+    full_refresh_every: u32,
+    partials_since_full: u32,
+    listeners: Vec<Listener>,
+}
+
+impl<B: DisplayBackend> App<B> {
+    /// Drives a single arrow, or a whole scene of them
+    pub fn new(backend: B, scene: impl Into<Scene>) -> Self {
+        Self {
+            frame: Frame::new(backend.size()),
+            backend,
+            scene: Arc::new(Mutex::new(scene.into())),
+            settle: Duration::ZERO,
+            state_file: None,
+            frame_file: None,
+            shown: Arc::default(),
+            full_refresh_every: 0,
+            partials_since_full: 0,
+            listeners: Vec::new(),
+        }
+    }
+
+    /// Draws the way the panel is mounted rather than in its native
+    /// portrait orientation
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.frame = Frame::rotated(self.backend.size(), orientation);
+    }
+
+    /// How long to keep collecting messages after the first one arrives before
+    /// refreshing, so presses in quick succession share a single refresh
+    pub fn set_settle(&mut self, settle: Duration) {
+        self.settle = settle;
+    }
+
+    /// Saves the arrows to `path` whenever they change
+    pub fn set_state_file(&mut self, path: PathBuf) {
+        self.state_file = Some(path);
+    }
+
+    /// Remembers each frame pushed to the panel in `path`, so a restart that
+    /// would draw the same frame again can leave the panel alone
+    pub fn set_frame_file(&mut self, path: PathBuf) {
+        self.frame_file = Some(path);
+    }
+
+    /// How many partial refreshes to do between full ones, on backends that
+    /// support them. Partial refreshes leave ghosts of the old image behind,
+    /// which only a full refresh clears. 0 always does full refreshes.
+    pub fn set_full_refresh_every(&mut self, partials: u32) {
+        self.full_refresh_every = partials;
+    }
+
+    /// Calls `listener` with the arrows whenever the panel has been brought
+    /// up to date with them
+    pub fn on_refresh<F: FnMut(&Scene) + Send + 'static>(&mut self, listener: F) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    pub fn scene(&self) -> Arc<Mutex<Scene>> {
+        Arc::clone(&self.scene)
+    }
+
+    /// What the panel shows, `None` until `start` has run
+    pub fn shown(&self) -> Arc<Mutex<Option<Shown>>> {
+        Arc::clone(&self.shown)
+    }
+
+    /// Initializes the panel and draws the arrows where they start, unless
+    /// the panel still shows exactly that from the last run
+    pub fn start(&mut self) -> Result<(), B::Error> {
+        self.backend.init()?;
+        self.save_state();
+        self.scene.lock().unwrap().draw(&mut self.frame);
+        if self.panel_shows_frame() {
+            println!("Panel is already up to date");
+            *self.shown.lock().unwrap() = Some(Shown::of(&self.frame));
+            self.notify();
+            return Ok(());
+        }
+        // A full refresh overwrites every pixel anyway, so there's no need
+        // to clear the panel first
+        self.push()
+    }
+
+    /// Applies a single message to the arrows and refreshes the panel
+    pub fn handle(&mut self, message: ArrowMessage) -> Result<(), B::Error> {
+        self.update(&[message])
+    }
+
+    /// Handles messages until a shutdown is requested or every sender has been
+    /// dropped. Everything that queued up during the settle window or while
+    /// the last refresh was running is applied together with a single refresh.
+    pub fn run(&mut self, rx: Receiver<ArrowMessage>) -> Result<(), B::Error> {
+        while let Ok(first) = rx.recv() {
+            let mut received = vec![first];
+            let settle_until = Instant::now() + self.settle;
+            while !matches!(received.last(), Some(ArrowMessage::Shutdown)) {
+                let wait = settle_until.saturating_duration_since(Instant::now());
+                match rx.recv_timeout(wait) {
+                    Ok(message) => received.push(message),
+                    Err(_) => break,
+                }
+            }
+            println!("Received {:?}", received);
+
+            self.update(&received)?;
+            if let Some(ArrowMessage::Shutdown) = received.last() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn sleep(&mut self) -> Result<(), B::Error> {
+        self.backend.sleep()
+    }
+
+    // Losing the saved position isn't worth stopping the arrow over
+    fn save_state(&self) {
+        let path = match &self.state_file {
+            Some(path) => path,
+            None => return,
+        };
+        let state = SceneState::of(&self.scene.lock().unwrap());
+        if let Err(source) = state.save(path) {
+            let path = path.clone();
+            eprintln!("error: {}", Error::State { path, source });
+        }
+    }
+
+    fn panel_shows_frame(&self) -> bool {
+        let path = match &self.frame_file {
+            Some(path) => path,
+            None => return false,
+        };
+        state::shows_frame(path, &self.frame).unwrap_or_else(|source| {
+            let path = path.clone();
+            eprintln!("error: {}", Error::State { path, source });
+            false
+        })
+    }
+
+    /// Applies messages in order, followed by a single refresh if anything
+    /// changed or one was asked for
+    fn update(&mut self, messages: &[ArrowMessage]) -> Result<(), B::Error> {
+        let mut changed = false;
+        {
+            let mut scene = self.scene.lock().unwrap();
+            for message in messages.iter() {
+                match scene.check(message) {
+                    Ok(()) => {
+                        changed |= scene.apply(message.clone());
+                        if *message == ArrowMessage::SelectNext {
+                            println!("Selected arrow {}", scene.selected().id);
+                        }
+                    }
+                    Err(e) => eprintln!("error: ignoring {:?}: {}", message, e),
+                }
+            }
+        }
+        if changed {
+            self.save_state();
+        }
+        if messages.contains(&ArrowMessage::Refresh) {
+            self.scene.lock().unwrap().draw(&mut self.frame);
+            return self.push();
+        }
+        if changed {
+            self.refresh()?;
+        }
+        Ok(())
+    }
+
+    /// Redraws the arrows and sends whatever changed to the panel
+    fn refresh(&mut self) -> Result<(), B::Error> {
+        self.scene.lock().unwrap().draw(&mut self.frame);
+        let shown = self.shown.lock().unwrap().clone();
+        let window = match shown {
+            Some(shown) => match self.frame.dirty_window(&shown.black, &shown.red) {
+                Some(window) => window,
+                // Nothing visible changed, like a move blocked by the edge
+                None => return Ok(()),
+            },
+            None => return self.push(),
+        };
+        if !self.backend.supports_partial() || self.partials_since_full >= self.full_refresh_every {
+            return self.push();
+        }
+        self.backend
+            .push_window(self.frame.black(), self.frame.red(), window)?;
+        self.partials_since_full += 1;
+        self.record_shown();
+        Ok(())
+    }
+
+    /// Sends the whole frame to the panel with a full refresh
+    fn push(&mut self) -> Result<(), B::Error> {
+        self.backend
+            .push_frame(self.frame.black(), self.frame.red())?;
+        self.partials_since_full = 0;
+        self.record_shown();
+        Ok(())
+    }
+
+    fn record_shown(&mut self) {
+        *self.shown.lock().unwrap() = Some(Shown::of(&self.frame));
+        if let Some(path) = &self.frame_file {
+            if let Err(source) = state::save_frame(path, &self.frame) {
+                let path = path.clone();
+                eprintln!("error: {}", Error::State { path, source });
+            }
+        }
+        self.notify();
+    }
+
+    fn notify(&mut self) {
+        let scene = self.scene.lock().unwrap();
+        for listener in self.listeners.iter_mut() {
+            listener(&scene);
+        }
+    }
+}
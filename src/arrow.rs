@@ -0,0 +1,548 @@
+use crate::frame::{Frame, Ink};
+use crate::panel::Model;
+use crate::trail::{Pose, Trail};
+use embedded_graphics::{
+    geometry::Point,
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle, Triangle},
+};
+use epd_waveshare::color::Black;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+/// What happens when the arrow is moved past the edge of the panel
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeMode {
+    /// Stop at the edge
+    #[default]
+    Clamp,
+    /// Come back in from the opposite edge
+    Wrap,
+    /// Reflect off the edge and turn around
+    Bounce,
+}
+
+impl EdgeMode {
+    /// Fits a coordinate into `min..=max`, returning whether the direction of
+    /// travel along that axis was reversed
+    fn fit(self, value: i32, min: i32, max: i32) -> (i32, bool) {
+        // Widened so positions far past the edge can't overflow
+        let (value, min, max) = (value as i64, min as i64, max as i64);
+        let span = max - min;
+        if span <= 0 {
+            return (min as i32, false);
+        }
+        let (position, reversed) = match self {
+            EdgeMode::Clamp => (value.max(min).min(max), false),
+            EdgeMode::Wrap => (min + (value - min).rem_euclid(span + 1), false),
+            EdgeMode::Bounce => {
+                if (min..=max).contains(&value) {
+                    return (value as i32, false);
+                }
+                let offset = (value - min).rem_euclid(2 * span);
+                let position = if offset <= span {
+                    offset
+                } else {
+                    2 * span - offset
+                };
+                let reflections = (value - min).div_euclid(span);
+                (min + position, reflections % 2 != 0)
+            }
+        };
+        // Always within min..=max, which came from i32s
+        (position as i32, reversed)
+    }
+}
+
+impl FromStr for EdgeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clamp" => Ok(EdgeMode::Clamp),
+            "wrap" => Ok(EdgeMode::Wrap),
+            "bounce" => Ok(EdgeMode::Bounce),
+            _ => Err(format!(
+                "unknown edge mode '{}', expected clamp, wrap or bounce",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for EdgeMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EdgeMode::Clamp => "clamp",
+            EdgeMode::Wrap => "wrap",
+            EdgeMode::Bounce => "bounce",
+        })
+    }
+}
+
+/// Direction the arrow points in, in tenths of a degree clockwise from
+/// straight down the panel, so a sixteenth of a turn comes out exact
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Heading(u16);
+
+impl Heading {
+    pub const DOWN: Heading = Heading(0);
+    pub const LEFT: Heading = Heading(900);
+    pub const UP: Heading = Heading(1800);
+    pub const RIGHT: Heading = Heading(2700);
+
+    /// Tenths of a degree in a full turn
+    pub const FULL_TURN: u16 = 3600;
+
+    pub fn from_degrees(degrees: i32) -> Self {
+        Self::from_tenths(degrees.rem_euclid(360) * 10)
+    }
+
+    pub fn from_tenths(tenths: i32) -> Self {
+        Heading(tenths.rem_euclid(Self::FULL_TURN as i32) as u16)
+    }
+
+    /// Nearest tenth of a degree, as saved and reported
+    pub fn from_degrees_f32(degrees: f32) -> Self {
+        Self::from_tenths(((degrees % 360.0) * 10.0).round() as i32)
+    }
+
+    pub fn degrees(self) -> f32 {
+        self.0 as f32 / 10.0
+    }
+
+    pub fn tenths(self) -> u16 {
+        self.0
+    }
+
+    /// Turns clockwise by `tenths` of a degree, or counter-clockwise for
+    /// negative ones
+    pub fn turned(self, tenths: i32) -> Self {
+        Self::from_tenths(self.0 as i32 + tenths)
+    }
+
+    /// Unit vector of the heading in panel coordinates
+    fn direction(self) -> (f32, f32) {
+        let (sin, cos) = self.degrees().to_radians().sin_cos();
+        (-sin, cos)
+    }
+}
+
+impl FromStr for Heading {
+    type Err = String;
+
+    /// Accepts a direction on the panel, like `up` or `east`, or degrees
+    /// clockwise from facing down
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "down" | "south" => Ok(Heading::DOWN),
+            "left" | "west" => Ok(Heading::LEFT),
+            "up" | "north" => Ok(Heading::UP),
+            "right" | "east" => Ok(Heading::RIGHT),
+            _ => s
+                .parse()
+                .ok()
+                .filter(|degrees: &f32| degrees.is_finite())
+                .map(Heading::from_degrees_f32)
+                .ok_or_else(|| {
+                    format!(
+                        "unknown heading '{}', expected up, down, left, right or degrees",
+                        s
+                    )
+                }),
+        }
+    }
+}
+
+impl fmt::Display for Heading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}°", self.degrees())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Part {
+    Shaft,
+    Head,
+}
+
+/// Ink for each part of the arrow
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ArrowStyle {
+    pub head: Ink,
+    pub shaft: Ink,
+}
+
+/// Tells the arrows in a scene apart
+pub type ArrowId = u8;
+
+pub struct Arrow {
+    pub id: ArrowId,
+    pub x: i32,
+    pub y: i32,
+    pub radius: i32,
+    pub heading: Heading,
+    /// Where `reset` puts the arrow, the top left corner facing down if
+    /// `None`
+    pub start: Option<Pose>,
+    /// Tenths of a degree turned by each rotation
+    pub turn: u16,
+    /// Area the whole arrow is kept inside of
+    pub bounds: Size,
+    pub edge_mode: EdgeMode,
+    pub style: ArrowStyle,
+    pub trail: Trail,
+}
+
+impl Arrow {
+    pub fn new(radius: i32) -> Self {
+        Self {
+            id: 1,
+            radius,
+            x: radius,
+            y: radius,
+            heading: Heading::DOWN,
+            start: None,
+            turn: 900,
+            bounds: Model::default().size(),
+            edge_mode: EdgeMode::default(),
+            style: ArrowStyle::default(),
+            trail: Trail::default(),
+        }
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+        frame.clear();
+        self.draw_trail(frame);
+        self.draw_over(frame);
+    }
+
+    pub(crate) fn draw_trail(&self, frame: &mut Frame) {
+        self.trail.draw(frame, self.radius);
+    }
+
+    /// Draws the arrow on top of whatever the frame already has
+    pub(crate) fn draw_over(&self, frame: &mut Frame) {
+        self.draw_part(frame.layer_mut(self.style.shaft), Part::Shaft);
+        self.draw_part(frame.layer_mut(self.style.head), Part::Head);
+    }
+
+    pub(crate) fn draw_part<D>(&self, target: &mut D, part: Part)
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let rect_size = Size::new(self.radius as u32, self.radius as u32);
+        let (rectangle, triangle) = match self.heading {
+            Heading::DOWN => (
+                Rectangle::new(
+                    Point::new(self.x - (self.radius / 2), self.y - self.radius),
+                    rect_size,
+                ),
+                Triangle::new(
+                    Point::new(self.x - self.radius, self.y),
+                    Point::new(self.x, self.y + self.radius),
+                    Point::new(self.x + self.radius, self.y),
+                ),
+            ),
+            Heading::LEFT => (
+                Rectangle::new(Point::new(self.x, self.y - (self.radius / 2)), rect_size),
+                Triangle::new(
+                    Point::new(self.x, self.y - self.radius),
+                    Point::new(self.x - self.radius, self.y),
+                    Point::new(self.x, self.y + self.radius),
+                ),
+            ),
+            Heading::UP => (
+                Rectangle::new(Point::new(self.x - (self.radius / 2), self.y), rect_size),
+                Triangle::new(
+                    Point::new(self.x - self.radius, self.y),
+                    Point::new(self.x, self.y - self.radius),
+                    Point::new(self.x + self.radius, self.y),
+                ),
+            ),
+            Heading::RIGHT => (
+                Rectangle::new(
+                    Point::new(self.x - self.radius, self.y - (self.radius / 2)),
+                    rect_size,
+                ),
+                Triangle::new(
+                    Point::new(self.x, self.y - self.radius),
+                    Point::new(self.x + self.radius, self.y),
+                    Point::new(self.x, self.y + self.radius),
+                ),
+            ),
+            _ => return self.draw_rotated_part(target, part),
+        };
+        let style = PrimitiveStyle::with_fill(Black);
+        let _ = match part {
+            Part::Shaft => rectangle.into_styled(style).draw(target),
+            Part::Head => triangle.into_styled(style).draw(target),
+        };
+    }
+
+    /// Draws headings between the four straight ones by rotating the outline
+    /// and filling it as triangles, two for the shaft and one for the head
+    fn draw_rotated_part<D>(&self, target: &mut D, part: Part)
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let (forward_x, forward_y) = self.heading.direction();
+        // Across the arrow, pointing right when it faces down
+        let (across_x, across_y) = (forward_y, -forward_x);
+        let point = |across: i32, forward: i32| {
+            let (across, forward) = (across as f32, forward as f32);
+            Point::new(
+                self.x + (across * across_x + forward * forward_x).round() as i32,
+                self.y + (across * across_y + forward * forward_y).round() as i32,
+            )
+        };
+
+        let r = self.radius;
+        let triangles = match part {
+            Part::Shaft => {
+                let shaft = [
+                    point(-r / 2, -r),
+                    point(r - r / 2, -r),
+                    point(r - r / 2, 0),
+                    point(-r / 2, 0),
+                ];
+                vec![
+                    Triangle::new(shaft[0], shaft[1], shaft[2]),
+                    Triangle::new(shaft[0], shaft[2], shaft[3]),
+                ]
+            }
+            Part::Head => vec![Triangle::new(point(-r, 0), point(0, r), point(r, 0))],
+        };
+        for triangle in triangles.iter() {
+            let _ = triangle
+                .into_styled(PrimitiveStyle::with_fill(Black))
+                .draw(target);
+        }
+    }
+
+    /// Turns clockwise by `turn`
+    pub fn rotate(&mut self) {
+        self.heading = self.heading.turned(self.turn as i32);
+    }
+
+    /// Turns counter-clockwise by `turn`
+    pub fn rotate_left(&mut self) {
+        self.heading = self.heading.turned(-(self.turn as i32));
+    }
+
+    /// Moves back to where the arrow started, the top left corner facing down
+    /// unless it was given somewhere else
+    pub fn reset(&mut self) {
+        match self.start {
+            Some(start) => {
+                let (x_range, y_range) = self.centers();
+                self.x = start.x.clamp(*x_range.start(), *x_range.end());
+                self.y = start.y.clamp(*y_range.start(), *y_range.end());
+                self.heading = start.heading;
+            }
+            None => {
+                self.x = self.radius;
+                self.y = self.radius;
+                self.heading = Heading::DOWN;
+            }
+        }
+        self.trail.clear();
+    }
+
+    pub fn move_forward(&mut self, distance: i32) {
+        let from = self.pose();
+        let (dx, dy) = self.heading.direction();
+        // Float to integer casts saturate, so only the additions can overflow
+        let target = (
+            self.x.saturating_add((dx * distance as f32).round() as i32),
+            self.y.saturating_add((dy * distance as f32).round() as i32),
+        );
+        self.x = target.0;
+        self.y = target.1;
+        self.keep_in_bounds();
+
+        let to = self.pose();
+        if (to.x, to.y) != (from.x, from.y) {
+            // Coming back in from the other side isn't a path worth drawing
+            let wrapped = self.edge_mode == EdgeMode::Wrap && (to.x, to.y) != target;
+            self.trail.record(from, to, !wrapped);
+        }
+    }
+
+    /// Jumps straight to `x`, `y`, or as close to it as the arrow fits
+    pub fn move_to(&mut self, x: i32, y: i32) {
+        let from = self.pose();
+        let (x_range, y_range) = self.centers();
+        self.x = x.clamp(*x_range.start(), *x_range.end());
+        self.y = y.clamp(*y_range.start(), *y_range.end());
+
+        let to = self.pose();
+        if (to.x, to.y) != (from.x, from.y) {
+            self.trail.record(from, to, true);
+        }
+    }
+
+    /// Resizes the arrow, moving it just far enough to stay on the panel
+    pub fn set_radius(&mut self, radius: i32) {
+        self.radius = radius.clamp(1, self.largest_radius());
+        let (x_range, y_range) = self.centers();
+        self.x = self.x.clamp(*x_range.start(), *x_range.end());
+        self.y = self.y.clamp(*y_range.start(), *y_range.end());
+    }
+
+    /// Checks that a message keeps the arrow on the panel and doesn't move it
+    /// further than the panel is long, saying why not if it does. Anything
+    /// else is always fine.
+    pub fn check(&self, message: &ArrowMessage) -> Result<(), String> {
+        match *message {
+            ArrowMessage::MoveTo { x, y } => {
+                let (x_range, y_range) = self.centers();
+                for (name, value, range) in [("x", x, x_range), ("y", y, y_range)] {
+                    if !range.contains(&value) {
+                        return Err(format!(
+                            "{} must be between {} and {} for the arrow to fit, got {}",
+                            name,
+                            range.start(),
+                            range.end(),
+                            value
+                        ));
+                    }
+                }
+                Ok(())
+            }
+            ArrowMessage::SetRadius(radius) => {
+                let largest = self.largest_radius();
+                if (1..=largest).contains(&radius) {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "radius must be between 1 and {}, got {}",
+                        largest, radius
+                    ))
+                }
+            }
+            ArrowMessage::MoveForward(distance) => {
+                let farthest = self.bounds.width.max(self.bounds.height) as i32;
+                if (-farthest..=farthest).contains(&distance) {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "distance must be between -{} and {}, got {}",
+                        farthest, farthest, distance
+                    ))
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Where the center can go with the whole arrow still on the panel
+    fn centers(&self) -> (RangeInclusive<i32>, RangeInclusive<i32>) {
+        let width = self.bounds.width as i32;
+        let height = self.bounds.height as i32;
+        (
+            self.radius..=(width - self.radius).max(self.radius),
+            self.radius..=(height - self.radius).max(self.radius),
+        )
+    }
+
+    fn largest_radius(&self) -> i32 {
+        (self.bounds.width.min(self.bounds.height) / 2).max(1) as i32
+    }
+
+    fn pose(&self) -> Pose {
+        Pose {
+            x: self.x,
+            y: self.y,
+            heading: self.heading,
+        }
+    }
+
+    /// Applies a message, returning whether it affects the arrow at all
+    pub fn apply(&mut self, message: ArrowMessage) -> bool {
+        match message {
+            ArrowMessage::MoveForward(distance) => self.move_forward(distance),
+            ArrowMessage::Rotate => self.rotate(),
+            ArrowMessage::RotateLeft => self.rotate_left(),
+            ArrowMessage::MoveTo { x, y } => self.move_to(x, y),
+            ArrowMessage::Face(heading) => self.heading = heading,
+            ArrowMessage::SetRadius(radius) => self.set_radius(radius),
+            ArrowMessage::Reset => self.reset(),
+            ArrowMessage::SelectNext
+            | ArrowMessage::To(..)
+            | ArrowMessage::Refresh
+            | ArrowMessage::Shutdown => return false,
+        }
+        true
+    }
+
+    /// Turns the arrow to face the opposite direction
+    pub fn reverse(&mut self) {
+        self.heading = self.heading.turned(1800);
+    }
+
+    fn keep_in_bounds(&mut self) {
+        let (x, x_reversed) =
+            self.edge_mode
+                .fit(self.x, self.radius, self.bounds.width as i32 - self.radius);
+        let (y, y_reversed) =
+            self.edge_mode
+                .fit(self.y, self.radius, self.bounds.height as i32 - self.radius);
+        self.x = x;
+        self.y = y;
+        // Reflect only the part of the motion along the axis that hit an edge
+        let tenths = self.heading.tenths() as i32;
+        self.heading = match (x_reversed, y_reversed) {
+            (false, false) => return,
+            (true, false) => Heading::from_tenths(-tenths),
+            (false, true) => Heading::from_tenths(1800 - tenths),
+            (true, true) => self.heading.turned(1800),
+        };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArrowMessage {
+    /// Rotate clockwise
+    Rotate,
+    RotateLeft,
+    MoveForward(i32),
+    /// Go straight to a point on the panel
+    MoveTo {
+        x: i32,
+        y: i32,
+    },
+    /// Turn to face a heading
+    Face(Heading),
+    /// Resize the arrow, in pixels from its center to its tip
+    SetRadius(i32),
+    /// Go back to the starting position and direction
+    Reset,
+    /// Send the following messages without a target to the next arrow in the
+    /// scene
+    SelectNext,
+    /// Aim a message at the arrow with this id rather than the selected one
+    To(ArrowId, Box<ArrowMessage>),
+    /// Redraw the whole panel with a full refresh, even if nothing changed
+    Refresh,
+    /// Stop handling messages so the panel can be put to sleep
+    Shutdown,
+}
+
+impl ArrowMessage {
+    /// This message for the arrow `id` only. Messages for the whole scene,
+    /// like `Refresh`, are left as they are.
+    pub fn to(self, id: ArrowId) -> Self {
+        match self {
+            ArrowMessage::SelectNext
+            | ArrowMessage::Refresh
+            | ArrowMessage::Shutdown
+            | ArrowMessage::To(..) => self,
+            message => ArrowMessage::To(id, Box::new(message)),
+        }
+    }
+}
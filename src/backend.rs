@@ -0,0 +1,609 @@
+use crate::error::Error;
+use crate::frame;
+use crate::panel::Model;
+use embedded_graphics::{geometry::Size, primitives::Rectangle};
+use embedded_hal::{blocking::spi, digital::v2};
+use epd_waveshare::{
+    epd1in54::Epd1in54, epd2in13_v2::Epd2in13, epd2in7b::Epd2in7b, epd4in2::Epd4in2, prelude::*,
+};
+use linux_embedded_hal::{Delay, Spidev};
+use rppal::gpio::{self, InputPin, OutputPin};
+use std::convert::Infallible;
+use std::io;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Something a rendered frame buffer can be pushed to, either the physical
+/// panel or an in-memory stand-in for running without a Pi
+pub trait DisplayBackend {
+    type Error;
+
+    /// Size of the panel in its native orientation
+    fn size(&self) -> Size;
+
+    /// Powers up the panel, or wakes it up again after `sleep`
+    fn init(&mut self) -> Result<(), Self::Error>;
+
+    /// Clears the panel to white
+    fn clear(&mut self) -> Result<(), Self::Error>;
+
+    /// Sends the black and red layers of a full frame to the panel and
+    /// refreshes it
+    fn push_frame(&mut self, black: &[u8], red: &[u8]) -> Result<(), Self::Error>;
+
+    /// Whether `push_window` refreshes less than the whole panel
+    fn supports_partial(&self) -> bool {
+        false
+    }
+
+    /// Sends the part of both layers inside a byte-aligned `window` and
+    /// refreshes just that area, or the whole panel if it can't do that
+    fn push_window(
+        &mut self,
+        black: &[u8],
+        red: &[u8],
+        window: Rectangle,
+    ) -> Result<(), Self::Error> {
+        let _ = window;
+        self.push_frame(black, red)
+    }
+
+    /// Puts the panel into deep sleep
+    fn sleep(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Chip select for the panel, either driven as a GPIO or left to the SPI
+/// controller when the panel sits on CE0/CE1
+pub enum ChipSelect {
+    Gpio(OutputPin),
+    Hardware,
+}
+
+impl v2::OutputPin for ChipSelect {
+    type Error = gpio::Error;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        match self {
+            ChipSelect::Gpio(pin) => {
+                pin.set_low();
+                Ok(())
+            }
+            ChipSelect::Hardware => Ok(()),
+        }
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        match self {
+            ChipSelect::Gpio(pin) => {
+                pin.set_high();
+                Ok(())
+            }
+            ChipSelect::Hardware => Ok(()),
+        }
+    }
+}
+
+/// Gives up on a panel that stays busy for longer than any operation takes,
+/// such as one that has come unplugged.
+///
+/// The drivers wait for BUSY in a loop with no way out other than an error
+/// from the pin, which they take to mean idle. So once a wait has gone on for
+/// too long the pin starts failing, nothing more is sent to the panel, and the
+/// next `check` reports which step it was.
+#[derive(Clone)]
+struct Watchdog(Arc<Mutex<WatchState>>);
+
+struct WatchState {
+    timeout: Duration,
+    waiting_since: Option<Instant>,
+    expired: bool,
+}
+
+impl Watchdog {
+    fn new(timeout: Duration) -> Self {
+        Watchdog(Arc::new(Mutex::new(WatchState {
+            timeout,
+            waiting_since: None,
+            expired: false,
+        })))
+    }
+
+    fn set_timeout(&self, timeout: Duration) {
+        self.0.lock().unwrap().timeout = timeout;
+    }
+
+    // Called on every poll of BUSY. The clock starts on the first poll after
+    // a check, so time spent idle between operations doesn't count.
+    fn poll(&self) -> io::Result<()> {
+        let mut state = self.0.lock().unwrap();
+        let since = *state.waiting_since.get_or_insert_with(Instant::now);
+        if state.expired || since.elapsed() > state.timeout {
+            state.expired = true;
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "panel stayed busy"));
+        }
+        Ok(())
+    }
+
+    fn expired(&self) -> bool {
+        self.0.lock().unwrap().expired
+    }
+
+    /// Fails if BUSY timed out since the last check, and starts over
+    fn check(&self, step: &'static str) -> Result<(), Error> {
+        let mut state = self.0.lock().unwrap();
+        let expired = state.expired;
+        state.waiting_since = None;
+        state.expired = false;
+        if expired {
+            return Err(Error::BusyTimeout { step });
+        }
+        Ok(())
+    }
+}
+
+/// The panel's BUSY pin, watched by a `Watchdog`
+struct BusyPin<P> {
+    pin: P,
+    watchdog: Watchdog,
+}
+
+impl<P: v2::InputPin> v2::InputPin for BusyPin<P> {
+    type Error = io::Error;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        self.watchdog.poll()?;
+        self.pin.is_high().map_err(|_| busy_unreadable())
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.watchdog.poll()?;
+        self.pin.is_low().map_err(|_| busy_unreadable())
+    }
+}
+
+fn busy_unreadable() -> io::Error {
+    io::Error::other("could not read BUSY")
+}
+
+/// The SPI bus, which stops passing commands on once the `Watchdog` has
+/// expired so the rest of a stalled step never reaches the panel
+struct WatchedSpi<S> {
+    spi: S,
+    watchdog: Watchdog,
+}
+
+impl<S: spi::Write<u8, Error = io::Error>> spi::Write<u8> for WatchedSpi<S> {
+    type Error = io::Error;
+
+    fn write(&mut self, words: &[u8]) -> io::Result<()> {
+        if self.watchdog.expired() {
+            return Ok(());
+        }
+        self.spi.write(words)
+    }
+}
+
+type Pins<CS, BUSY, OUT> = (CS, BusyPin<BUSY>, OUT, OUT);
+
+/// The driver for whichever panel is connected
+enum Driver<S, CS, BUSY, OUT> {
+    Epd2in7b(Epd2in7b<WatchedSpi<S>, CS, BusyPin<BUSY>, OUT, OUT, Delay>),
+    Epd2in13V2(Epd2in13<WatchedSpi<S>, CS, BusyPin<BUSY>, OUT, OUT, Delay>),
+    Epd4in2(Epd4in2<WatchedSpi<S>, CS, BusyPin<BUSY>, OUT, OUT, Delay>),
+    Epd1in54(Epd1in54<WatchedSpi<S>, CS, BusyPin<BUSY>, OUT, OUT, Delay>),
+}
+
+/// Runs the same code against the driver for any model
+macro_rules! with_driver {
+    ($driver:expr, $epd:ident => $body:expr) => {
+        match $driver {
+            Driver::Epd2in7b($epd) => $body,
+            Driver::Epd2in13V2($epd) => $body,
+            Driver::Epd4in2($epd) => $body,
+            Driver::Epd1in54($epd) => $body,
+        }
+    };
+}
+
+impl<S, CS, BUSY, OUT> Driver<S, CS, BUSY, OUT>
+where
+    S: spi::Write<u8, Error = io::Error>,
+    CS: v2::OutputPin,
+    BUSY: v2::InputPin,
+    OUT: v2::OutputPin,
+{
+    fn new(
+        model: Model,
+        spi: &mut WatchedSpi<S>,
+        pins: Pins<CS, BUSY, OUT>,
+        delay: &mut Delay,
+    ) -> io::Result<Self> {
+        let (cs, busy, dc, rst) = pins;
+        Ok(match model {
+            Model::Epd2in7b => Driver::Epd2in7b(Epd2in7b::new(spi, cs, busy, dc, rst, delay)?),
+            Model::Epd2in13V2 => Driver::Epd2in13V2(Epd2in13::new(spi, cs, busy, dc, rst, delay)?),
+            Model::Epd4in2 => Driver::Epd4in2(Epd4in2::new(spi, cs, busy, dc, rst, delay)?),
+            Model::Epd1in54 => Driver::Epd1in54(Epd1in54::new(spi, cs, busy, dc, rst, delay)?),
+        })
+    }
+
+    /// Switches between the full waveform and the quick one partial refreshes
+    /// use. The drivers keep the choice across `wake_up`, so a reset doesn't
+    /// undo it.
+    fn set_refresh(
+        &mut self,
+        spi: &mut WatchedSpi<S>,
+        delay: &mut Delay,
+        lut: RefreshLut,
+    ) -> io::Result<()> {
+        match self {
+            Driver::Epd2in7b(_) => Ok(()),
+            // Re-initializes the panel, but only when the mode changes
+            Driver::Epd2in13V2(epd) => epd.set_refresh(spi, delay, lut),
+            Driver::Epd4in2(epd) => epd.set_lut(spi, Some(lut)),
+            Driver::Epd1in54(epd) => epd.set_lut(spi, Some(lut)),
+        }
+    }
+}
+
+/// A Waveshare panel driven over SPI and GPIO.
+///
+/// The tri-color 2.7" panel gets both layers and always does full refreshes.
+/// The black and white panels get red drawn in black, and refresh just the
+/// changed window with the quick waveform when asked to, switching back to the
+/// full one for full refreshes.
+///
+/// A panel that stays busy for longer than the timeout is reset through RST
+/// and the operation tried again, a limited number of times.
+///
+/// On a Pi the bus and pins are the ones rppal and `Spidev` open, but anything
+/// implementing the embedded-hal traits will do.
+pub struct EpdBackend<S = Spidev, CS = ChipSelect, BUSY = InputPin, OUT = OutputPin> {
+    model: Model,
+    spi: WatchedSpi<S>,
+    delay: Delay,
+    // Handed over to the driver on the first call to `init`
+    pins: Option<Pins<CS, BUSY, OUT>>,
+    epd: Option<Driver<S, CS, BUSY, OUT>>,
+    watchdog: Watchdog,
+    retries: u32,
+}
+
+impl<S, CS, BUSY, OUT> EpdBackend<S, CS, BUSY, OUT>
+where
+    S: spi::Write<u8, Error = io::Error>,
+    CS: v2::OutputPin,
+    BUSY: v2::InputPin,
+    OUT: v2::OutputPin,
+{
+    pub fn new(model: Model, spi: S, cs: CS, busy: BUSY, dc: OUT, rst: OUT) -> Self {
+        let watchdog = Watchdog::new(Duration::from_secs(30));
+        let busy = BusyPin {
+            pin: busy,
+            watchdog: watchdog.clone(),
+        };
+        let spi = WatchedSpi {
+            spi,
+            watchdog: watchdog.clone(),
+        };
+        Self {
+            model,
+            spi,
+            delay: Delay {},
+            pins: Some((cs, busy, dc, rst)),
+            epd: None,
+            watchdog,
+            retries: 2,
+        }
+    }
+
+    /// How long the panel may stay busy during a single step, and how many
+    /// times to reset it and try again before giving up. Defaults to 30
+    /// seconds and 2 retries.
+    pub fn set_busy_timeout(&mut self, timeout: Duration, retries: u32) {
+        self.watchdog.set_timeout(timeout);
+        self.retries = retries;
+    }
+
+    /// Runs `operation` against the driver, and if the panel stays busy
+    /// resets it and runs the operation again
+    fn watched<F>(&mut self, first_step: &'static str, mut operation: F) -> Result<(), Error>
+    where
+        F: FnMut(
+            &mut Driver<S, CS, BUSY, OUT>,
+            &mut WatchedSpi<S>,
+            &mut Delay,
+            &Watchdog,
+        ) -> Result<(), Error>,
+    {
+        let epd = self
+            .epd
+            .as_mut()
+            .ok_or_else(|| not_initialized(first_step))?;
+        let (spi, delay) = (&mut self.spi, &mut self.delay);
+        let mut retries = 0;
+        loop {
+            match operation(epd, spi, delay, &self.watchdog) {
+                Err(Error::BusyTimeout { step: stalled }) if retries < self.retries => {
+                    retries += 1;
+                    eprintln!(
+                        "eink stayed busy while {}, resetting it (retry {} of {})",
+                        stalled, retries, self.retries
+                    );
+                    with_driver!(epd, epd => epd.wake_up(spi, delay)).map_err(step("resetting"))?;
+                    self.watchdog.check("resetting")?;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Attaches the step that was being performed to an error from the driver
+fn step(step: &'static str) -> impl FnOnce(io::Error) -> Error {
+    move |source| Error::Display { step, source }
+}
+
+fn not_initialized(step: &'static str) -> Error {
+    Error::Display {
+        step,
+        source: io::Error::other("not initialized"),
+    }
+}
+
+/// Combines both layers for a panel without red, where red pixels are black
+fn fold_red(black: &[u8], red: &[u8]) -> Vec<u8> {
+    black
+        .iter()
+        .zip(red)
+        .map(|(black, red)| black & red)
+        .collect()
+}
+
+impl<S, CS, BUSY, OUT> DisplayBackend for EpdBackend<S, CS, BUSY, OUT>
+where
+    S: spi::Write<u8, Error = io::Error>,
+    CS: v2::OutputPin,
+    BUSY: v2::InputPin,
+    OUT: v2::OutputPin,
+{
+    type Error = Error;
+
+    fn size(&self) -> Size {
+        self.model.size()
+    }
+
+    fn init(&mut self) -> Result<(), Self::Error> {
+        let step = match self.epd.as_mut() {
+            Some(epd) => {
+                with_driver!(epd, epd => epd.wake_up(&mut self.spi, &mut self.delay))
+                    .map_err(step("waking up"))?;
+                "waking up"
+            }
+            None => {
+                let pins = self.pins.take().expect("pins already handed to eink");
+                let epd = Driver::new(self.model, &mut self.spi, pins, &mut self.delay)
+                    .map_err(step("initializing"))?;
+                self.epd = Some(epd);
+                "initializing"
+            }
+        };
+        // Resetting initializes the panel again, so there's nothing to redo
+        self.watched(step, |_, _, _, watchdog| watchdog.check(step))
+    }
+
+    fn clear(&mut self) -> Result<(), Self::Error> {
+        self.watched("clearing", |epd, spi, delay, watchdog| {
+            with_driver!(epd, epd => epd.clear_frame(spi, delay)).map_err(step("clearing"))?;
+            watchdog.check("clearing")
+        })
+    }
+
+    fn push_frame(&mut self, black: &[u8], red: &[u8]) -> Result<(), Self::Error> {
+        let folded = fold_red(black, red);
+        self.watched("switching waveform", |epd, spi, delay, watchdog| {
+            epd.set_refresh(spi, delay, RefreshLut::Full)
+                .map_err(step("switching waveform"))?;
+            watchdog.check("switching waveform")?;
+            match &mut *epd {
+                Driver::Epd2in7b(epd) => epd.update_color_frame(spi, black, red),
+                epd => with_driver!(epd, epd => epd.update_frame(spi, &folded, delay)),
+            }
+            .map_err(step("updating frame"))?;
+            watchdog.check("updating frame")?;
+            with_driver!(epd, epd => epd.display_frame(spi, delay))
+                .map_err(step("displaying frame"))?;
+            watchdog.check("displaying frame")
+        })
+    }
+
+    fn supports_partial(&self) -> bool {
+        self.model.supports_partial()
+    }
+
+    fn push_window(
+        &mut self,
+        black: &[u8],
+        red: &[u8],
+        window: Rectangle,
+    ) -> Result<(), Self::Error> {
+        if !self.model.supports_partial() {
+            return self.push_frame(black, red);
+        }
+        let folded = fold_red(black, red);
+        let bytes = frame::crop(&folded, self.model.size().width, &window);
+        let (x, y) = (window.top_left.x as u32, window.top_left.y as u32);
+        let Size { width, height } = window.size;
+        self.watched("switching waveform", |epd, spi, delay, watchdog| {
+            epd.set_refresh(spi, delay, RefreshLut::Quick)
+                .map_err(step("switching waveform"))?;
+            watchdog.check("switching waveform")?;
+            match &mut *epd {
+                // In quick mode the 2.13" panel compares the whole frame
+                // against the last one, and only the pixels that differ change
+                Driver::Epd2in13V2(epd) => {
+                    epd.update_frame(spi, &folded, delay)
+                        .map_err(step("updating window"))?;
+                    watchdog.check("updating window")?;
+                    epd.display_frame(spi, delay)
+                        .map_err(step("displaying frame"))?;
+                    watchdog.check("displaying frame")?;
+                    return epd
+                        .set_partial_base_buffer(spi, &folded)
+                        .map_err(step("updating window"));
+                }
+                epd => with_driver!(epd, epd => {
+                    epd.update_partial_frame(spi, &bytes, x, y, width, height)
+                }),
+            }
+            .map_err(step("updating window"))?;
+            watchdog.check("updating window")?;
+            with_driver!(epd, epd => epd.display_frame(spi, delay))
+                .map_err(step("displaying frame"))?;
+            watchdog.check("displaying frame")
+        })
+    }
+
+    fn sleep(&mut self) -> Result<(), Self::Error> {
+        self.watched("sleeping", |epd, spi, delay, watchdog| {
+            with_driver!(epd, epd => epd.sleep(spi, delay)).map_err(step("sleeping"))?;
+            watchdog.check("sleeping")
+        })
+    }
+}
+
+/// Keeps the last pushed frame in memory instead of sending it anywhere
+pub struct MemoryBackend {
+    model: Model,
+    frame: Vec<u8>,
+    red: Vec<u8>,
+    awake: bool,
+    refreshes: usize,
+    partial: bool,
+    partial_refreshes: usize,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::for_model(Model::default())
+    }
+
+    /// Stands in for the given panel, refreshing and showing red only if
+    /// the panel could
+    pub fn for_model(model: Model) -> Self {
+        let white = frame::Layer::new(model.size()).buffer().to_vec();
+        Self {
+            model,
+            frame: white.clone(),
+            red: white,
+            awake: false,
+            refreshes: 0,
+            partial: model.supports_partial(),
+            partial_refreshes: 0,
+        }
+    }
+
+    /// The black layer as it would currently appear on the panel
+    pub fn frame(&self) -> &[u8] {
+        &self.frame
+    }
+
+    /// The red layer as it would currently appear on the panel
+    pub fn red(&self) -> &[u8] {
+        &self.red
+    }
+
+    /// Number of full refreshes, including clears, since creation
+    pub fn refreshes(&self) -> usize {
+        self.refreshes
+    }
+
+    /// Overrides whether partial refreshes are supported
+    pub fn set_partial(&mut self, supported: bool) {
+        self.partial = supported;
+    }
+
+    /// Number of partial refreshes since creation
+    pub fn partial_refreshes(&self) -> usize {
+        self.partial_refreshes
+    }
+
+    pub fn is_awake(&self) -> bool {
+        self.awake
+    }
+
+    // What a panel without red makes of the red layer
+    fn store(&mut self, black: &[u8], red: &[u8], bytes: Range<usize>) {
+        if self.model.has_red() {
+            self.frame[bytes.clone()].copy_from_slice(&black[bytes.clone()]);
+            self.red[bytes.clone()].copy_from_slice(&red[bytes]);
+        } else {
+            self.frame[bytes.clone()]
+                .copy_from_slice(&fold_red(&black[bytes.clone()], &red[bytes]));
+        }
+    }
+}
+
+impl Default for MemoryBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DisplayBackend for MemoryBackend {
+    type Error = Infallible;
+
+    fn size(&self) -> Size {
+        self.model.size()
+    }
+
+    fn init(&mut self) -> Result<(), Self::Error> {
+        self.awake = true;
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<(), Self::Error> {
+        for byte in self.frame.iter_mut().chain(self.red.iter_mut()) {
+            *byte = Color::White.get_byte_value();
+        }
+        self.refreshes += 1;
+        Ok(())
+    }
+
+    fn push_frame(&mut self, black: &[u8], red: &[u8]) -> Result<(), Self::Error> {
+        self.store(black, red, 0..black.len());
+        self.refreshes += 1;
+        Ok(())
+    }
+
+    fn supports_partial(&self) -> bool {
+        self.partial
+    }
+
+    fn push_window(
+        &mut self,
+        black: &[u8],
+        red: &[u8],
+        window: Rectangle,
+    ) -> Result<(), Self::Error> {
+        if !self.partial {
+            return self.push_frame(black, red);
+        }
+        let row_bytes = black.len() / self.model.size().height as usize;
+        let left = window.top_left.x as usize / 8;
+        let width = window.size.width.div_ceil(8) as usize;
+        let top = window.top_left.y as usize;
+        for row in top..top + window.size.height as usize {
+            let start = row * row_bytes + left;
+            self.store(black, red, start..start + width);
+        }
+        self.partial_refreshes += 1;
+        Ok(())
+    }
+
+    fn sleep(&mut self) -> Result<(), Self::Error> {
+        self.awake = false;
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+#[cfg(feature = "mqtt")]
+use eink_arrow::mqtt::Mqtt;
+use eink_arrow::{
+    app::App,
+    arrow::ArrowMessage,
+    backend::{DisplayBackend, MemoryBackend},
+    cli::Args,
+    config::Config,
+    frame,
+    http::HttpApi,
+    panel::Orientation,
+    script,
+};
+use embedded_graphics::{geometry::Size, primitives::Rectangle};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read};
+use std::path::PathBuf;
+use std::process;
+use std::sync::mpsc;
+use std::thread;
+
+// Runs the arrow against an in-memory panel, writing every refresh to a PNG
+// in the output directory (`frames` unless given as an argument). The arrow
+// and player sections of the config file and the command line overrides apply
+// as usual.
+//
+// Keys are read from stdin, so press enter after typing them:
+//   m - move forward
+//   r - rotate clockwise
+//   l - rotate counter-clockwise
+//   x - reset to the starting position
+//   n - select the next arrow, with more than one `[[player]]`
+//   q - quit
+//
+// `--script <file>` runs the commands in a file instead, and `--stdin` reads
+// the same commands from stdin a line at a time, quitting once they run out.
+//
+// With `--http <address:port>` the HTTP API is served as well, and with the
+// `mqtt` feature and `mqtt.enabled` set the MQTT client runs too.
+
+/// Writes each frame pushed to the in-memory panel out as a numbered PNG
+struct PngBackend {
+    memory: MemoryBackend,
+    orientation: Orientation,
+    dir: PathBuf,
+}
+
+impl PngBackend {
+    fn write_frame(&self) -> io::Result<()> {
+        let number = self.memory.refreshes() + self.memory.partial_refreshes();
+        let path = self.dir.join(format!("frame-{:04}.png", number));
+        let file = BufWriter::new(File::create(&path)?);
+        let size = self.memory.size();
+        let (black, red) = (self.memory.frame(), self.memory.red());
+        frame::write_png(file, size, self.orientation, black, red).map_err(io::Error::other)?;
+        println!("Wrote {}", path.display());
+        Ok(())
+    }
+}
+
+impl DisplayBackend for PngBackend {
+    type Error = io::Error;
+
+    fn size(&self) -> Size {
+        self.memory.size()
+    }
+
+    fn init(&mut self) -> io::Result<()> {
+        self.memory.init().unwrap_or_else(|never| match never {});
+        Ok(())
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        self.memory.clear().unwrap_or_else(|never| match never {});
+        self.write_frame()
+    }
+
+    fn push_frame(&mut self, black: &[u8], red: &[u8]) -> io::Result<()> {
+        self.memory
+            .push_frame(black, red)
+            .unwrap_or_else(|never| match never {});
+        self.write_frame()
+    }
+
+    fn supports_partial(&self) -> bool {
+        self.memory.supports_partial()
+    }
+
+    fn push_window(&mut self, black: &[u8], red: &[u8], window: Rectangle) -> io::Result<()> {
+        self.memory
+            .push_window(black, red, window)
+            .unwrap_or_else(|never| match never {});
+        self.write_frame()
+    }
+
+    fn sleep(&mut self) -> io::Result<()> {
+        self.memory.sleep().unwrap_or_else(|never| match never {});
+        Ok(())
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?;
+    if args.print_default_config {
+        print!("{}", Config::default_toml(args.profile.unwrap_or_default()));
+        return Ok(());
+    }
+    let config = Config::load(&args)?;
+    let script = match &args.script {
+        Some(path) => Some(script::load(path, config.arrow.move_distance)?),
+        None => None,
+    };
+
+    let scene = config.scene();
+
+    let dir = PathBuf::from(args.positional.first().map_or("frames", String::as_str));
+    fs::create_dir_all(&dir)?;
+
+    let backend = PngBackend {
+        memory: MemoryBackend::for_model(config.display.model),
+        orientation: config.display.orientation,
+        dir,
+    };
+    let mut app = App::new(backend, scene);
+    app.set_orientation(config.display.orientation);
+    app.set_settle(config.display.settle());
+    app.set_full_refresh_every(config.display.full_refresh_every);
+
+    let (tx, rx) = mpsc::channel();
+    #[cfg(feature = "mqtt")]
+    {
+        if config.mqtt.enabled {
+            let mqtt = Mqtt::start(&config.mqtt, tx.clone(), config.arrow.move_distance);
+            app.on_refresh(move |scene| mqtt.publish(scene));
+        }
+    }
+    app.start()?;
+    println!("Initialized");
+
+    let distance = config.arrow.move_distance;
+    if config.http.enabled {
+        let api = HttpApi::new(
+            tx.clone(),
+            app.scene(),
+            app.shown(),
+            config.display.model.size(),
+            config.display.orientation,
+            distance,
+        );
+        let address = api.spawn(&config.http.bind)?;
+        println!("Serving HTTP API on http://{}", address);
+    }
+    if let Some(commands) = script {
+        script::play(commands, tx);
+        println!("Running script");
+    } else if args.stdin {
+        script::follow(BufReader::new(io::stdin()), distance, tx);
+        println!("Waiting for commands");
+    } else {
+        thread::spawn(move || {
+            for byte in io::stdin().lock().bytes() {
+                let message = match byte {
+                    Ok(b'm') => ArrowMessage::MoveForward(distance),
+                    Ok(b'r') => ArrowMessage::Rotate,
+                    Ok(b'l') => ArrowMessage::RotateLeft,
+                    Ok(b'x') => ArrowMessage::Reset,
+                    Ok(b'n') => ArrowMessage::SelectNext,
+                    Ok(b'q') | Err(_) => break,
+                    Ok(_) => continue,
+                };
+                if tx.send(message).is_err() {
+                    break;
+                }
+            }
+            // The HTTP API may still hold a sender, so ask to stop outright
+            let _ = tx.send(ArrowMessage::Shutdown);
+        });
+        println!("Waiting for input (m: move, r/l: rotate, x: reset, n: next arrow, q: quit)");
+    }
+
+    app.run(rx)?;
+
+    println!("Finished, going to sleep");
+    app.sleep()?;
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
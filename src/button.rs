@@ -0,0 +1,205 @@
+use crate::arrow::ArrowMessage;
+use rppal::gpio::{InputPin, Level, Trigger};
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A complete gesture on a single button
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Press {
+    Short,
+    /// Held down for at least the long press duration
+    Long,
+    /// Two short presses in quick succession
+    Double,
+}
+
+/// What a press on a button does to the arrow
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    None,
+    Move,
+    MoveBack,
+    /// Same as `RotateRight`
+    Rotate,
+    RotateLeft,
+    RotateRight,
+    Reset,
+    /// Hand the buttons without an arrow of their own to the next arrow
+    SelectNext,
+}
+
+impl Action {
+    pub fn message(self, distance: i32) -> Option<ArrowMessage> {
+        match self {
+            Action::None => None,
+            Action::Move => Some(ArrowMessage::MoveForward(distance)),
+            Action::MoveBack => Some(ArrowMessage::MoveForward(-distance)),
+            Action::Rotate | Action::RotateRight => Some(ArrowMessage::Rotate),
+            Action::RotateLeft => Some(ArrowMessage::RotateLeft),
+            Action::Reset => Some(ArrowMessage::Reset),
+            Action::SelectNext => Some(ArrowMessage::SelectNext),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct PressTiming {
+    /// Edges closer together than this after an accepted edge are contact bounce
+    pub debounce: Duration,
+    /// Presses held at least this long are long presses
+    pub long_press: Duration,
+    /// Longest gap between two short presses for them to count as a double
+    /// press, zero to report every short press straight away
+    pub double_press: Duration,
+}
+
+/// Turns the raw edges from a button into debounced presses
+#[derive(Debug)]
+pub struct PressDetector {
+    timing: PressTiming,
+    last_edge: Option<Instant>,
+    pressed_at: Option<Instant>,
+    // Last edge dropped as bounce, if it left the button in the other state.
+    // It counts once nothing has changed for the debounce time after it.
+    unsettled: Option<(bool, Instant)>,
+    // Release of a short press that may still turn into a double press
+    pending_release: Option<Instant>,
+    // Release of a long press, held back while the short press before it is
+    // reported
+    long_release: Option<Instant>,
+}
+
+impl PressDetector {
+    pub fn new(timing: PressTiming) -> Self {
+        Self {
+            timing,
+            last_edge: None,
+            pressed_at: None,
+            unsettled: None,
+            pending_release: None,
+            long_release: None,
+        }
+    }
+
+    /// Feeds an edge seen at `at`, returning a press if it completed one
+    pub fn edge(&mut self, pressed: bool, at: Instant) -> Option<Press> {
+        let bouncing = self
+            .last_edge
+            .is_some_and(|last| at.duration_since(last) < self.timing.debounce);
+        if bouncing {
+            self.unsettled = (pressed != self.pressed_at.is_some()).then_some((pressed, at));
+            return None;
+        }
+        // A tap shorter than the debounce time has its release dropped above
+        let settled = self.settle(at);
+        self.unsettled = None;
+        if pressed == self.pressed_at.is_some() {
+            return settled;
+        }
+        settled.or(self.accept(pressed, at))
+    }
+
+    fn settle(&mut self, now: Instant) -> Option<Press> {
+        let (pressed, at) = self.unsettled?;
+        if now < at + self.timing.debounce {
+            return None;
+        }
+        self.unsettled = None;
+        self.accept(pressed, at)
+    }
+
+    fn accept(&mut self, pressed: bool, at: Instant) -> Option<Press> {
+        self.last_edge = Some(at);
+        if pressed {
+            self.pressed_at = Some(at);
+            return None;
+        }
+        let held = at.duration_since(self.pressed_at.take()?);
+        if held >= self.timing.long_press {
+            if self.pending_release.take().is_some() {
+                self.long_release = Some(at);
+                return Some(Press::Short);
+            }
+            Some(Press::Long)
+        } else if self.pending_release.take().is_some() {
+            Some(Press::Double)
+        } else if self.timing.double_press == Duration::from_millis(0) {
+            Some(Press::Short)
+        } else {
+            self.pending_release = Some(at);
+            None
+        }
+    }
+
+    /// Reports a press that completed without another edge: a short press
+    /// once it's too late for it to become a double press, a long press held
+    /// back behind one, or a tap that bounced
+    pub fn poll(&mut self, now: Instant) -> Option<Press> {
+        if let Some(press) = self.settle(now) {
+            return Some(press);
+        }
+        if self.long_release.take().is_some() {
+            return Some(Press::Long);
+        }
+        if now < self.double_press_deadline()? {
+            return None;
+        }
+        self.pending_release = None;
+        Some(Press::Short)
+    }
+
+    /// When `poll` next needs to be called, if at all
+    pub fn deadline(&self) -> Option<Instant> {
+        let settles = self.unsettled.map(|(_, at)| at + self.timing.debounce);
+        [self.long_release, settles, self.double_press_deadline()]
+            .iter()
+            .flatten()
+            .min()
+            .copied()
+    }
+
+    fn double_press_deadline(&self) -> Option<Instant> {
+        if self.pressed_at.is_some() {
+            return None;
+        }
+        self.pending_release
+            .map(|released| released + self.timing.double_press)
+    }
+}
+
+/// Watches a pulled-up button, which reads low while pressed, calling
+/// `on_press` from a background thread for every detected press
+pub fn watch<F>(pin: &mut InputPin, timing: PressTiming, mut on_press: F) -> rppal::gpio::Result<()>
+where
+    F: FnMut(Press) + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    pin.set_async_interrupt(Trigger::Both, move |level: Level| {
+        let _ = tx.send((level == Level::Low, Instant::now()));
+    })?;
+
+    thread::spawn(move || {
+        let mut detector = PressDetector::new(timing);
+        loop {
+            let edge = match detector.deadline() {
+                Some(deadline) => {
+                    rx.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                }
+                None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            let press = match edge {
+                Ok((pressed, at)) => detector.edge(pressed, at),
+                Err(RecvTimeoutError::Timeout) => detector.poll(Instant::now()),
+                // The interrupt was cleared along with the pin
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            if let Some(press) = press {
+                on_press(press);
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use crate::arrow::EdgeMode;
+use crate::config::{ConfigError, Profile};
+use crate::panel::{Model, Orientation};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Command line options shared by the binaries
+#[derive(Debug, Default)]
+pub struct Args {
+    /// `--config <file>`
+    pub config: Option<PathBuf>,
+    /// `--print-default-config`
+    pub print_default_config: bool,
+    /// `--reset-state`
+    pub reset_state: bool,
+    /// `--profile <breakout|hat>`
+    pub profile: Option<Profile>,
+    /// `--model <epd2in7b|epd2in13_v2|epd4in2|epd1in54>`
+    pub model: Option<Model>,
+    /// `--orientation <portrait|landscape|portrait-flipped|landscape-flipped>`
+    pub orientation: Option<Orientation>,
+    /// `--radius <pixels>`
+    pub radius: Option<i32>,
+    /// `--distance <pixels>`
+    pub distance: Option<i32>,
+    /// `--edge <clamp|wrap|bounce>`
+    pub edge: Option<EdgeMode>,
+    /// `--headings <count>`
+    pub headings: Option<u16>,
+    /// `--trail <length>`
+    pub trail: Option<usize>,
+    /// `--http <address:port>`
+    pub http: Option<String>,
+    /// `--script <file>`
+    pub script: Option<PathBuf>,
+    /// `--stdin`
+    pub stdin: bool,
+    /// Anything that isn't an option
+    pub positional: Vec<String>,
+}
+
+impl Args {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, ConfigError> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--config" => parsed.config = Some(value(&arg, args.next())?),
+                "--print-default-config" => parsed.print_default_config = true,
+                "--reset-state" => parsed.reset_state = true,
+                "--profile" => parsed.profile = Some(value(&arg, args.next())?),
+                "--model" => parsed.model = Some(value(&arg, args.next())?),
+                "--orientation" => parsed.orientation = Some(value(&arg, args.next())?),
+                "--radius" => parsed.radius = Some(value(&arg, args.next())?),
+                "--distance" => parsed.distance = Some(value(&arg, args.next())?),
+                "--edge" => parsed.edge = Some(value(&arg, args.next())?),
+                "--headings" => parsed.headings = Some(value(&arg, args.next())?),
+                "--trail" => parsed.trail = Some(value(&arg, args.next())?),
+                "--http" => parsed.http = Some(value(&arg, args.next())?),
+                "--script" => parsed.script = Some(value(&arg, args.next())?),
+                "--stdin" => parsed.stdin = true,
+                _ if arg.starts_with("--") => {
+                    return Err(ConfigError::Args(format!("unknown option {}", arg)))
+                }
+                _ => parsed.positional.push(arg),
+            }
+        }
+        if parsed.script.is_some() && parsed.stdin {
+            return Err(ConfigError::Args(
+                "--script and --stdin can't be used together".into(),
+            ));
+        }
+        Ok(parsed)
+    }
+}
+
+fn value<T>(option: &str, value: Option<String>) -> Result<T, ConfigError>
+where
+    T: FromStr,
+    T::Err: ToString,
+{
+    let value = value.ok_or_else(|| ConfigError::Args(format!("{} needs a value", option)))?;
+    value
+        .parse()
+        .map_err(|e: T::Err| ConfigError::Args(format!("{} {}: {}", option, value, e.to_string())))
+}
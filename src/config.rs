@@ -0,0 +1,764 @@
+use crate::arrow::{Arrow, ArrowId, ArrowMessage, ArrowStyle, EdgeMode, Heading};
+use crate::button::{Action, Press, PressTiming};
+use crate::cli::Args;
+use crate::frame::Ink;
+use crate::panel::{Model, Orientation};
+use crate::scene::Scene;
+use crate::trail::{Pose, Trail, TrailStyle};
+use embedded_graphics::geometry::Size;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Config file read when no `--config` is given, if it exists
+pub const DEFAULT_CONFIG_PATH: &str = "eink-arrow.toml";
+
+/// Starting point for the pin and button setup, which the rest of the config
+/// file is applied on top of
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Profile {
+    /// Panel wired to BCM 5, 19, 6 and 13 with external buttons on 20 and 21
+    #[default]
+    Breakout,
+    /// Stock pinout of the Waveshare 2.7" e-Paper HAT, using its four keys
+    Hat,
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "breakout" => Ok(Profile::Breakout),
+            "hat" => Ok(Profile::Hat),
+            _ => Err(format!("unknown profile '{}', expected breakout or hat", s)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub profile: Profile,
+    pub spi: SpiConfig,
+    pub pins: PinConfig,
+    pub display: DisplayConfig,
+    pub input: InputConfig,
+    pub arrow: ArrowConfig,
+    pub state: StateConfig,
+    pub http: HttpConfig,
+    pub mqtt: MqttConfig,
+    #[serde(rename = "player", skip_serializing_if = "Vec::is_empty")]
+    pub players: Vec<PlayerConfig>,
+    #[serde(rename = "button")]
+    pub buttons: Vec<ButtonConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::for_profile(Profile::default())
+    }
+}
+
+impl Config {
+    pub fn for_profile(profile: Profile) -> Self {
+        let (pins, buttons) = match profile {
+            Profile::Breakout => (
+                PinConfig {
+                    cs: CsPin::Gpio(5), // pin 29
+                    busy: 19,           // pin 35
+                    dc: 6,              // pin 31
+                    rst: 13,            // pin 33
+                },
+                vec![
+                    // closest to ethernet
+                    ButtonConfig::new("move", 20, Action::Move),
+                    // furthest from output
+                    ButtonConfig::new("rotate", 21, Action::Rotate),
+                ],
+            ),
+            Profile::Hat => (
+                PinConfig {
+                    // CE0, driven by the SPI controller
+                    cs: CsPin::Hardware,
+                    busy: 24,
+                    dc: 25,
+                    rst: 17,
+                },
+                vec![
+                    ButtonConfig::new("key1", 5, Action::RotateLeft),
+                    ButtonConfig::new("key2", 6, Action::RotateRight),
+                    ButtonConfig::new("key3", 13, Action::Move),
+                    ButtonConfig::new("key4", 19, Action::Reset),
+                ],
+            ),
+        };
+        Self {
+            profile,
+            spi: SpiConfig::default(),
+            pins,
+            display: DisplayConfig::default(),
+            input: InputConfig::default(),
+            arrow: ArrowConfig::default(),
+            state: StateConfig::default(),
+            http: HttpConfig::default(),
+            mqtt: MqttConfig::default(),
+            players: Vec::new(),
+            buttons,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpiConfig {
+    pub device: PathBuf,
+    pub speed_hz: u32,
+}
+
+impl Default for SpiConfig {
+    fn default() -> Self {
+        Self {
+            device: PathBuf::from("/dev/spidev0.0"),
+            speed_hz: 4_000_000,
+        }
+    }
+}
+
+/// BCM numbers of the pins wired to the panel
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PinConfig {
+    #[serde(default)]
+    pub cs: CsPin,
+    pub busy: u8,
+    pub dc: u8,
+    pub rst: u8,
+}
+
+/// The panel's chip select, written as a BCM number or `"hardware"`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "CsValue", into = "CsValue")]
+pub enum CsPin {
+    /// The SPI controller's own CE line for the device
+    #[default]
+    Hardware,
+    Gpio(u8),
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum CsValue {
+    Pin(u8),
+    Name(String),
+}
+
+impl TryFrom<CsValue> for CsPin {
+    type Error = String;
+
+    fn try_from(value: CsValue) -> Result<Self, Self::Error> {
+        match value {
+            CsValue::Pin(pin) => Ok(CsPin::Gpio(pin)),
+            CsValue::Name(name) if name == "hardware" => Ok(CsPin::Hardware),
+            CsValue::Name(name) => Err(format!(
+                "unknown chip select '{}', expected a GPIO number or hardware",
+                name
+            )),
+        }
+    }
+}
+
+impl From<CsPin> for CsValue {
+    fn from(cs: CsPin) -> Self {
+        match cs {
+            CsPin::Hardware => CsValue::Name("hardware".into()),
+            CsPin::Gpio(pin) => CsValue::Pin(pin),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    pub model: Model,
+    /// Which way up the panel is mounted
+    pub orientation: Orientation,
+    /// How long to wait for more input after a press before refreshing
+    pub settle_ms: u64,
+    /// Partial refreshes between full ones, on panels that can do them
+    pub full_refresh_every: u32,
+    /// How long the panel may stay busy before it's reset
+    pub busy_timeout_ms: u64,
+    /// Resets to try before giving up on a panel that stays busy
+    pub busy_retries: u32,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            model: Model::default(),
+            orientation: Orientation::default(),
+            settle_ms: 500,
+            full_refresh_every: 10,
+            busy_timeout_ms: 30_000,
+            busy_retries: 2,
+        }
+    }
+}
+
+impl DisplayConfig {
+    pub fn settle(&self) -> Duration {
+        Duration::from_millis(self.settle_ms)
+    }
+
+    pub fn busy_timeout(&self) -> Duration {
+        Duration::from_millis(self.busy_timeout_ms)
+    }
+
+    /// Size of the panel as mounted
+    pub fn size(&self) -> Size {
+        self.orientation.size(self.model.size())
+    }
+}
+
+/// How button presses are told apart, shared by all buttons
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputConfig {
+    pub debounce_ms: u64,
+    pub long_press_ms: u64,
+    pub double_press_ms: u64,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            debounce_ms: 50,
+            long_press_ms: 800,
+            double_press_ms: 300,
+        }
+    }
+}
+
+/// A button wired to a BCM pin, which is pulled up and pressed when low
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ButtonConfig {
+    /// Shown in log messages
+    #[serde(default)]
+    pub name: String,
+    pub pin: u8,
+    #[serde(default = "no_action")]
+    pub short: Action,
+    #[serde(default = "no_action")]
+    pub long: Action,
+    #[serde(default = "no_action")]
+    pub double: Action,
+    /// The arrow the button drives, the selected one if left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arrow: Option<ArrowId>,
+}
+
+fn no_action() -> Action {
+    Action::None
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArrowConfig {
+    /// Scales with the panel when left out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub radius: Option<i32>,
+    pub move_distance: i32,
+    pub edge: EdgeMode,
+    /// Number of directions a full turn is divided into, 4 for right angles
+    /// and 360 for single degrees
+    pub headings: u16,
+    pub head: Ink,
+    pub shaft: Ink,
+    pub trail: TrailConfig,
+}
+
+impl Default for ArrowConfig {
+    fn default() -> Self {
+        Self {
+            radius: None,
+            move_distance: 100,
+            edge: EdgeMode::default(),
+            headings: 4,
+            head: Ink::default(),
+            shaft: Ink::default(),
+            trail: TrailConfig::default(),
+        }
+    }
+}
+
+impl ArrowConfig {
+    /// An arrow in its starting position on the panel `display` describes,
+    /// with these settings
+    pub fn arrow(&self, display: &DisplayConfig) -> Arrow {
+        let radius = self
+            .radius
+            .unwrap_or_else(|| display.model.default_radius());
+        let mut arrow = Arrow::new(radius);
+        arrow.bounds = display.size();
+        arrow.edge_mode = self.edge;
+        arrow.turn = Heading::FULL_TURN / self.headings;
+        arrow.style = ArrowStyle {
+            head: self.head,
+            shaft: self.shaft,
+        };
+        arrow.trail = Trail::new(self.trail.length, self.trail.style, self.trail.ink);
+        arrow
+    }
+}
+
+/// One of several arrows sharing the panel, each drawn and moved with the
+/// `[arrow]` settings
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlayerConfig {
+    pub id: ArrowId,
+    /// Ink for the whole arrow, instead of `arrow.head` and `arrow.shaft`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ink: Option<Ink>,
+    /// Where the arrow starts and resets to, the top left corner if left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<i32>,
+    /// Degrees clockwise from facing down
+    #[serde(default)]
+    pub heading: u16,
+}
+
+impl PlayerConfig {
+    /// The player's arrow in its starting position
+    pub fn arrow(&self, settings: &ArrowConfig, display: &DisplayConfig) -> Arrow {
+        let mut arrow = settings.arrow(display);
+        arrow.id = self.id;
+        if let Some(ink) = self.ink {
+            arrow.style = ArrowStyle {
+                head: ink,
+                shaft: ink,
+            };
+        }
+        arrow.start = Some(Pose {
+            x: self.x.unwrap_or(arrow.radius),
+            y: self.y.unwrap_or(arrow.radius),
+            heading: Heading::from_degrees(self.heading as i32),
+        });
+        arrow.reset();
+        arrow
+    }
+}
+
+/// Earlier positions drawn behind the arrow
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrailConfig {
+    /// How many earlier positions to keep, 0 for no trail
+    pub length: usize,
+    pub style: TrailStyle,
+    pub ink: Ink,
+}
+
+/// Where the arrow's position is saved so it survives a restart
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StateConfig {
+    pub enabled: bool,
+    pub path: PathBuf,
+    /// Copy of the last frame sent to the panel
+    pub frame_path: PathBuf,
+}
+
+impl Default for StateConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: PathBuf::from("eink-arrow-state.toml"),
+            frame_path: PathBuf::from("eink-arrow-frame.bin"),
+        }
+    }
+}
+
+/// The HTTP API, see `http::HttpApi`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    pub enabled: bool,
+    /// Address and port to listen on, only reachable from the Pi itself by
+    /// default
+    pub bind: String,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: "127.0.0.1:8080".into(),
+        }
+    }
+}
+
+/// The MQTT client, which needs the `mqtt` feature
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MqttConfig {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    /// Commands are read from and the arrow published to topics under this
+    pub topic: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "localhost".into(),
+            port: 1883,
+            client_id: "eink-arrow".into(),
+            topic: "eink-arrow".into(),
+            username: None,
+            password: None,
+        }
+    }
+}
+
+// Highest GPIO number broken out on the 40-pin header
+const MAX_BCM_PIN: u8 = 27;
+
+// Pins the kernel claims for SPI0 once it's enabled, whether or not the
+// panel uses the controller's own chip selects
+const SPI0_PINS: [(&str, u8); 5] = [
+    ("SPI0 CE1", 7),
+    ("SPI0 CE0", 8),
+    ("SPI0 MISO", 9),
+    ("SPI0 MOSI", 10),
+    ("SPI0 SCLK", 11),
+];
+
+impl Config {
+    /// Reads the config file picked by the command line, falling back to the
+    /// defaults if none was given and `eink-arrow.toml` doesn't exist, then
+    /// applies any overrides from the command line and validates the result
+    pub fn load(args: &Args) -> Result<Self, ConfigError> {
+        let mut config = match &args.config {
+            Some(path) => Self::from_file(path, args.profile)?,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_PATH), args.profile)?
+            }
+            None => Self::for_profile(args.profile.unwrap_or_default()),
+        };
+        if let Some(radius) = args.radius {
+            config.arrow.radius = Some(radius);
+        }
+        if let Some(model) = args.model {
+            config.display.model = model;
+        }
+        if let Some(orientation) = args.orientation {
+            config.display.orientation = orientation;
+        }
+        if let Some(distance) = args.distance {
+            config.arrow.move_distance = distance;
+        }
+        if let Some(edge) = args.edge {
+            config.arrow.edge = edge;
+        }
+        if let Some(length) = args.trail {
+            config.arrow.trail.length = length;
+        }
+        if let Some(headings) = args.headings {
+            config.arrow.headings = headings;
+        }
+        if let Some(bind) = &args.http {
+            config.http.enabled = true;
+            config.http.bind = bind.clone();
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reads a config file on top of its profile, or `profile` if given
+    pub fn from_file(path: &Path, profile: Option<Profile>) -> Result<Self, ConfigError> {
+        let parse_error = |e| ConfigError::Parse(path.to_path_buf(), e);
+        let contents =
+            fs::read_to_string(path).map_err(|e| ConfigError::Read(path.to_path_buf(), e))?;
+        let mut file: toml::Value = toml::from_str(&contents).map_err(parse_error)?;
+        let file_profile = match file.as_table_mut().and_then(|t| t.remove("profile")) {
+            Some(value) => Some(value.try_into().map_err(parse_error)?),
+            None => None,
+        };
+        let profile = profile.or(file_profile).unwrap_or_default();
+
+        let mut config =
+            toml::Value::try_from(Self::for_profile(profile)).expect("profile serializes");
+        merge(&mut config, file);
+        config.try_into().map_err(parse_error)
+    }
+
+    /// Every arrow on the panel in its starting position, one for each
+    /// `[[player]]` or just the one without any
+    pub fn scene(&self) -> Scene {
+        if self.players.is_empty() {
+            return self.arrow.arrow(&self.display).into();
+        }
+        Scene::new(
+            self.players
+                .iter()
+                .map(|player| player.arrow(&self.arrow, &self.display))
+                .collect(),
+        )
+    }
+
+    /// The defaults for a profile as TOML, as a starting point for a config file
+    pub fn default_toml(profile: Profile) -> String {
+        toml::to_string_pretty(&Self::for_profile(profile)).expect("default config serializes")
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.spi.speed_hz == 0 {
+            return Err(ConfigError::Invalid("spi.speed_hz must be above 0".into()));
+        }
+        if self.mqtt.enabled && !cfg!(feature = "mqtt") {
+            return Err(ConfigError::Invalid(
+                "mqtt.enabled needs eink-arrow built with --features mqtt".into(),
+            ));
+        }
+        if self.display.busy_timeout_ms == 0 {
+            return Err(ConfigError::Invalid(
+                "display.busy_timeout_ms must be above 0".into(),
+            ));
+        }
+        let size = self.display.model.size();
+        let largest_radius = size.width.min(size.height) as i32 / 2;
+        match self.arrow.radius {
+            Some(radius) if radius <= 0 || radius > largest_radius => {
+                return Err(ConfigError::Invalid(format!(
+                    "arrow.radius must be between 1 and {} on the {} panel, got {}",
+                    largest_radius, self.display.model, radius
+                )));
+            }
+            _ => {}
+        }
+        let farthest = size.width.max(size.height) as i32;
+        if !(-farthest..=farthest).contains(&self.arrow.move_distance) {
+            return Err(ConfigError::Invalid(format!(
+                "arrow.move_distance must be between -{} and {} on the {} panel, got {}",
+                farthest, farthest, self.display.model, self.arrow.move_distance
+            )));
+        }
+        if !Heading::FULL_TURN.is_multiple_of(self.arrow.headings) {
+            return Err(ConfigError::Invalid(format!(
+                "arrow.headings must split a turn into whole tenths of a degree, like 4, 8 or 16, got {}",
+                self.arrow.headings
+            )));
+        }
+
+        let mut ids = Vec::new();
+        for player in &self.players {
+            if ids.contains(&player.id) {
+                return Err(ConfigError::Invalid(format!(
+                    "player id {} is used more than once",
+                    player.id
+                )));
+            }
+            ids.push(player.id);
+            if player.heading >= 360 {
+                return Err(ConfigError::Invalid(format!(
+                    "player {} heading must be below 360 degrees, got {}",
+                    player.id, player.heading
+                )));
+            }
+            let arrow = player.arrow(&self.arrow, &self.display);
+            let start = ArrowMessage::MoveTo {
+                x: player.x.unwrap_or(arrow.radius),
+                y: player.y.unwrap_or(arrow.radius),
+            };
+            arrow.check(&start).map_err(|e| {
+                ConfigError::Invalid(format!("player {} starts off the panel: {}", player.id, e))
+            })?;
+        }
+        if ids.is_empty() {
+            ids.push(self.arrow.arrow(&self.display).id);
+        }
+        for button in &self.buttons {
+            match button.arrow {
+                Some(id) if !ids.contains(&id) => {
+                    return Err(ConfigError::Invalid(format!(
+                        "button {} drives arrow {}, but there is no player with that id",
+                        button.label(),
+                        id
+                    )));
+                }
+                _ => {}
+            }
+        }
+
+        if self.input.debounce_ms >= self.input.long_press_ms {
+            return Err(ConfigError::Invalid(
+                "input.long_press_ms must be longer than input.debounce_ms".into(),
+            ));
+        }
+
+        if self.input.double_press_ms == 0 && self.buttons.iter().any(|b| b.double != Action::None)
+        {
+            return Err(ConfigError::Invalid(
+                "input.double_press_ms must be above 0 to use double press actions".into(),
+            ));
+        }
+
+        let mut pins = Vec::new();
+        if self
+            .spi
+            .device
+            .to_string_lossy()
+            .starts_with("/dev/spidev0.")
+        {
+            for &(name, pin) in SPI0_PINS.iter() {
+                pins.push((name.to_string(), pin));
+            }
+        }
+        pins.push(("pins.busy".to_string(), self.pins.busy));
+        pins.push(("pins.dc".to_string(), self.pins.dc));
+        pins.push(("pins.rst".to_string(), self.pins.rst));
+        if let CsPin::Gpio(cs) = self.pins.cs {
+            pins.push(("pins.cs".to_string(), cs));
+        }
+        for button in &self.buttons {
+            pins.push((format!("button {}", button.label()), button.pin));
+        }
+        for (i, (name, pin)) in pins.iter().enumerate() {
+            if *pin > MAX_BCM_PIN {
+                return Err(ConfigError::Invalid(format!(
+                    "{} is set to GPIO {}, but only 0 to {} are available",
+                    name, pin, MAX_BCM_PIN
+                )));
+            }
+            if let Some((other, _)) = pins[..i].iter().find(|(_, p)| p == pin) {
+                return Err(ConfigError::Invalid(format!(
+                    "GPIO {} is used for both {} and {}",
+                    pin, other, name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ButtonConfig {
+    fn new(name: &str, pin: u8, short: Action) -> Self {
+        Self {
+            name: name.into(),
+            pin,
+            short,
+            long: Action::None,
+            double: Action::None,
+            arrow: None,
+        }
+    }
+
+    /// Press timing for this button. Buttons without a long or double press
+    /// action treat those as short presses, so short presses aren't held back
+    /// waiting for a second press that wouldn't do anything.
+    pub fn timing(&self, input: &InputConfig) -> PressTiming {
+        PressTiming {
+            debounce: Duration::from_millis(input.debounce_ms),
+            long_press: match self.long {
+                Action::None => Duration::MAX,
+                _ => Duration::from_millis(input.long_press_ms),
+            },
+            double_press: match self.double {
+                Action::None => Duration::ZERO,
+                _ => Duration::from_millis(input.double_press_ms),
+            },
+        }
+    }
+
+    pub fn action(&self, press: Press) -> Action {
+        match press {
+            Press::Short => self.short,
+            Press::Long => self.long,
+            Press::Double => self.double,
+        }
+    }
+
+    /// What a press does, aimed at the button's own arrow if it has one
+    pub fn message(&self, press: Press, distance: i32) -> Option<ArrowMessage> {
+        let message = self.action(press).message(distance)?;
+        Some(match self.arrow {
+            Some(id) => message.to(id),
+            None => message,
+        })
+    }
+
+    /// The button's name, or its pin if it doesn't have one
+    pub fn label(&self) -> String {
+        if self.name.is_empty() {
+            format!("on GPIO {}", self.pin)
+        } else {
+            format!("'{}'", self.name)
+        }
+    }
+}
+
+/// Recursively overwrites values in `base` with those in `overlay`, keeping
+/// anything in `base` tables that `overlay` doesn't mention
+fn merge(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Args(String),
+    Read(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Args(msg) => write!(f, "{}", msg),
+            ConfigError::Read(path, e) => write!(f, "could not read {}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "invalid config {}: {}", path.display(), e),
+            ConfigError::Invalid(msg) => write!(f, "invalid config: {}", msg),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Read(_, e) => Some(e),
+            ConfigError::Parse(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::arrow::ArrowMessage;
+use crate::config::ConfigError;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc::SendError;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The config file or command line options were invalid
+    Config(ConfigError),
+    /// The SPI device couldn't be opened or configured
+    Spi { device: PathBuf, source: io::Error },
+    /// A panel control pin couldn't be set up
+    Gpio {
+        name: &'static str,
+        pin: u8,
+        source: rppal::gpio::Error,
+    },
+    /// A button input couldn't be set up
+    Input {
+        context: String,
+        source: rppal::gpio::Error,
+    },
+    /// Talking to the panel failed partway through an operation
+    Display {
+        step: &'static str,
+        source: io::Error,
+    },
+    /// The panel kept its BUSY pin high for longer than any operation takes
+    BusyTimeout { step: &'static str },
+    /// The saved arrow state couldn't be read or written
+    State { path: PathBuf, source: io::Error },
+    /// The signal handlers couldn't be registered
+    Signal(io::Error),
+    /// The HTTP API couldn't listen on its address
+    Http {
+        bind: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// A command script couldn't be read, or has a line that makes no sense
+    Script {
+        path: PathBuf,
+        line: Option<usize>,
+        message: String,
+    },
+    /// A message was sent after the event loop had already stopped
+    Channel(SendError<ArrowMessage>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Config(e) => write!(f, "{}", e),
+            Error::Spi { device, source } => write!(
+                f,
+                "could not set up SPI device {}: {} (is SPI enabled in raspi-config?)",
+                device.display(),
+                source
+            ),
+            Error::Gpio { name, pin, source } => write!(
+                f,
+                "could not set up {} on GPIO {}: {} (is the pin free and is the user in the gpio group?)",
+                name, pin, source
+            ),
+            Error::Input { context, source } => write!(f, "{}: {}", context, source),
+            Error::Display { step, source } => {
+                write!(f, "eink failed while {}: {}", step, source)
+            }
+            Error::BusyTimeout { step } => write!(
+                f,
+                "eink stayed busy while {}, check that the panel is connected",
+                step
+            ),
+            Error::State { path, source } => write!(
+                f,
+                "could not use arrow state file {}: {} (--reset-state starts over without it)",
+                path.display(),
+                source
+            ),
+            Error::Signal(e) => write!(f, "could not register signal handlers: {}", e),
+            Error::Http { bind, source } => {
+                write!(f, "could not serve HTTP API on {}: {}", bind, source)
+            }
+            Error::Script {
+                path,
+                line: Some(line),
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+            Error::Script {
+                path,
+                line: None,
+                message,
+            } => write!(f, "could not read script {}: {}", path.display(), message),
+            Error::Channel(e) => write!(f, "could not send {:?}, event loop has stopped", e.0),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Config(e) => Some(e),
+            Error::Spi { source, .. } => Some(source),
+            Error::Gpio { source, .. } => Some(source),
+            Error::Input { source, .. } => Some(source),
+            Error::Display { source, .. } => Some(source),
+            Error::State { source, .. } => Some(source),
+            Error::Signal(e) => Some(e),
+            Error::Http { source, .. } => Some(source.as_ref()),
+            Error::Channel(e) => Some(e),
+            Error::BusyTimeout { .. } | Error::Script { .. } => None,
+        }
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(e: ConfigError) -> Self {
+        Error::Config(e)
+    }
+}
+
+impl From<SendError<ArrowMessage>> for Error {
+    fn from(e: SendError<ArrowMessage>) -> Self {
+        Error::Channel(e)
+    }
+}
@@ -0,0 +1,238 @@
+use crate::panel::{Model, Orientation};
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*, primitives::Rectangle};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::io::Write;
+
+const PALETTE: [u8; 9] = [
+    0xff, 0xff, 0xff, // white
+    0x00, 0x00, 0x00, // black
+    0xff, 0x00, 0x00, // red
+];
+
+/// Which of the panel's two inks something is drawn in
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ink {
+    #[default]
+    Black,
+    Red,
+}
+
+/// A single-color image laid out the way the panels expect it: one bit per
+/// pixel, most significant bit first, rows padded to whole bytes and set
+/// bits white. It is drawn into the way the panel is mounted and laid out in
+/// the panel's native orientation.
+pub struct Layer {
+    size: Size,
+    orientation: Orientation,
+    buffer: Vec<u8>,
+}
+
+impl Layer {
+    pub fn new(size: Size) -> Self {
+        Self::rotated(size, Orientation::default())
+    }
+
+    /// A layer for a panel `size` pixels in its native orientation, mounted
+    /// the `orientation` way
+    pub fn rotated(size: Size, orientation: Orientation) -> Self {
+        Self {
+            size,
+            orientation,
+            buffer: vec![0xff; row_bytes(size) * size.height as usize],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for byte in self.buffer.iter_mut() {
+            *byte = 0xff;
+        }
+    }
+
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+}
+
+impl DrawTarget for Layer {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let row_bytes = row_bytes(self.size);
+        for Pixel(point, color) in pixels {
+            let point = self.orientation.to_native(point, self.size);
+            if point.x < 0 || point.y < 0 {
+                continue;
+            }
+            let (x, y) = (point.x as u32, point.y as u32);
+            if x >= self.size.width || y >= self.size.height {
+                continue;
+            }
+            let index = x as usize / 8 + y as usize * row_bytes;
+            let mask = 0x80 >> (x % 8);
+            match color {
+                BinaryColor::On => self.buffer[index] &= !mask,
+                BinaryColor::Off => self.buffer[index] |= mask,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl OriginDimensions for Layer {
+    fn size(&self) -> Size {
+        self.orientation.size(self.size)
+    }
+}
+
+fn row_bytes(size: Size) -> usize {
+    (size.width as usize).div_ceil(8)
+}
+
+/// A black and a red layer making up a single image on the panel.
+///
+/// Both are drawn into with `Black`, which in the red layer marks the pixels
+/// that come out red. Red takes precedence where the layers overlap.
+pub struct Frame {
+    black: Layer,
+    red: Layer,
+}
+
+impl Frame {
+    pub fn new(size: Size) -> Self {
+        Self::rotated(size, Orientation::default())
+    }
+
+    /// A frame for a panel `size` pixels in its native orientation, mounted
+    /// the `orientation` way
+    pub fn rotated(size: Size, orientation: Orientation) -> Self {
+        Self {
+            black: Layer::rotated(size, orientation),
+            red: Layer::rotated(size, orientation),
+        }
+    }
+
+    /// Size in the panel's native orientation, as the buffers are laid out
+    pub fn size(&self) -> Size {
+        self.black.size
+    }
+
+    /// Sets every pixel in both layers back to white
+    pub fn clear(&mut self) {
+        self.black.clear();
+        self.red.clear();
+    }
+
+    /// The layer to draw something in `ink` into
+    pub fn layer_mut(&mut self, ink: Ink) -> &mut Layer {
+        match ink {
+            Ink::Black => &mut self.black,
+            Ink::Red => &mut self.red,
+        }
+    }
+
+    pub fn black(&self) -> &[u8] {
+        self.black.buffer()
+    }
+
+    pub fn red(&self) -> &[u8] {
+        self.red.buffer()
+    }
+
+    /// Smallest area, widened to whole bytes but no wider than the panel,
+    /// outside of which this frame matches the given layers, or `None` if it
+    /// matches them everywhere
+    pub fn dirty_window(&self, black: &[u8], red: &[u8]) -> Option<Rectangle> {
+        let row_bytes = row_bytes(self.size());
+        let changed =
+            |index: usize| self.black()[index] != black[index] || self.red()[index] != red[index];
+        let (mut left, mut right, mut top, mut bottom) = (usize::MAX, 0, usize::MAX, 0);
+        for index in (0..black.len()).filter(|&index| changed(index)) {
+            let (column, row) = (index % row_bytes, index / row_bytes);
+            left = left.min(column);
+            right = right.max(column);
+            top = top.min(row);
+            bottom = bottom.max(row);
+        }
+        if left > right {
+            return None;
+        }
+        // The last byte of a row can run past the edge of a panel whose width
+        // isn't a multiple of 8
+        let (left, right) = (left as u32 * 8, (right as u32 + 1) * 8);
+        Some(Rectangle::new(
+            Point::new(left as i32, top as i32),
+            Size::new(
+                right.min(self.size().width) - left,
+                (bottom - top + 1) as u32,
+            ),
+        ))
+    }
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self::new(Model::default().size())
+    }
+}
+
+/// Copies the rows of a layer `width` pixels wide that lie inside a
+/// byte-aligned `window`, as the drivers' partial updates expect them. A
+/// window that ends at the edge of the panel takes the whole last byte.
+pub fn crop(buffer: &[u8], width: u32, window: &Rectangle) -> Vec<u8> {
+    let row_bytes = row_bytes(Size::new(width, 0));
+    let left = window.top_left.x as usize / 8;
+    let window_bytes = window.size.width.div_ceil(8) as usize;
+    let top = window.top_left.y as usize;
+    (top..top + window.size.height as usize)
+        .flat_map(|row| {
+            let start = row * row_bytes + left;
+            buffer[start..start + window_bytes].iter().copied()
+        })
+        .collect()
+}
+
+/// Encodes the black and red layers of a frame for a panel `size` pixels in
+/// its native orientation as a single PNG, turned the way the panel is mounted
+pub fn write_png<W: Write>(
+    writer: W,
+    size: Size,
+    orientation: Orientation,
+    black: &[u8],
+    red: &[u8],
+) -> Result<(), png::EncodingError> {
+    let seen = orientation.size(size);
+    let mut encoder = png::Encoder::new(writer, seen.width, seen.height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(PALETTE.to_vec());
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&indexed_pixels(size, orientation, black, red))
+}
+
+// One palette index per pixel, set bits in either buffer are white
+fn indexed_pixels(size: Size, orientation: Orientation, black: &[u8], red: &[u8]) -> Vec<u8> {
+    let row_bytes = row_bytes(size);
+    let seen = orientation.size(size);
+    (0..seen.height as i32)
+        .flat_map(|y| (0..seen.width as i32).map(move |x| Point::new(x, y)))
+        .map(|point| {
+            let native = orientation.to_native(point, size);
+            let (x, y) = (native.x as usize, native.y as usize);
+            let index = x / 8 + y * row_bytes;
+            let mask = 0x80 >> (x % 8);
+            if red[index] & mask == 0 {
+                2
+            } else if black[index] & mask == 0 {
+                1
+            } else {
+                0
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,215 @@
+use crate::app::Shown;
+use crate::arrow::{ArrowId, ArrowMessage};
+use crate::error::{Error, Result};
+use crate::frame;
+use crate::panel::Orientation;
+use crate::scene::Scene;
+use crate::state::ScenePosition;
+use embedded_graphics::geometry::Size;
+use std::net::SocketAddr;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tiny_http::{Header, Method, Response, Server};
+
+/// Lets other programs drive the arrow over HTTP, alongside the buttons:
+///
+/// - `POST /rotate` turns clockwise, or counter-clockwise with `?direction=left`
+/// - `POST /move` moves forward, by `?distance=<pixels>` if given
+/// - `POST /goto?x=<x>&y=<y>` jumps straight to a point on the panel
+/// - `POST /face?heading=<heading>` turns to `up`, `east`, `90` and the like
+/// - `POST /radius?radius=<pixels>` resizes the arrow
+/// - `POST /reset` goes back to the starting position
+/// - `GET /state` is every arrow's position and heading as JSON, along with
+///   which one is selected
+/// - `GET /frame.png` is what the panel shows
+///
+/// Moves and turns are queued with the button presses, and answered with
+/// `202 Accepted` before the panel has refreshed. Ones that would put the
+/// arrow off the panel are turned away with `400 Bad Request`. They go to the
+/// arrow given as `?arrow=<id>`, or the selected one without it.
+pub struct HttpApi {
+    tx: Sender<ArrowMessage>,
+    scene: Arc<Mutex<Scene>>,
+    shown: Arc<Mutex<Option<Shown>>>,
+    size: Size,
+    orientation: Orientation,
+    distance: i32,
+}
+
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Reply {
+    fn text(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: body.into().into_bytes(),
+        }
+    }
+}
+
+impl HttpApi {
+    /// Serves the arrows and frame shared by an `App`, sending moves and
+    /// turns to the same channel it reads from. `size` and `orientation` are
+    /// the panel's, and `distance` is how far a move without one goes.
+    pub fn new(
+        tx: Sender<ArrowMessage>,
+        scene: Arc<Mutex<Scene>>,
+        shown: Arc<Mutex<Option<Shown>>>,
+        size: Size,
+        orientation: Orientation,
+        distance: i32,
+    ) -> Self {
+        Self {
+            tx,
+            scene,
+            shown,
+            size,
+            orientation,
+            distance,
+        }
+    }
+
+    /// Starts answering requests on `bind` in the background, returning the
+    /// address it ended up listening on
+    pub fn spawn(self, bind: &str) -> Result<SocketAddr> {
+        let http_error = |source| Error::Http {
+            bind: bind.to_string(),
+            source,
+        };
+        let server = Server::http(bind).map_err(http_error)?;
+        let address = server
+            .server_addr()
+            .to_ip()
+            .expect("bound to an IP address");
+        thread::spawn(move || self.serve(server));
+        Ok(address)
+    }
+
+    fn serve(self, server: Server) {
+        for request in server.incoming_requests() {
+            let reply = self.respond(request.method(), request.url());
+            let content_type = Header::from_bytes("Content-Type", reply.content_type)
+                .expect("content type is a valid header");
+            let response = Response::from_data(reply.body)
+                .with_status_code(reply.status)
+                .with_header(content_type);
+            if let Err(e) = request.respond(response) {
+                eprintln!("error: could not answer HTTP request: {}", e);
+            }
+        }
+    }
+
+    fn respond(&self, method: &Method, url: &str) -> Reply {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        match (method, path) {
+            (Method::Post, "/rotate") => match param(query, "direction") {
+                None | Some("right") => self.send(query, ArrowMessage::Rotate),
+                Some("left") => self.send(query, ArrowMessage::RotateLeft),
+                Some(direction) => Reply::text(
+                    400,
+                    format!("unknown direction '{}', expected left or right", direction),
+                ),
+            },
+            (Method::Post, "/move") => match param(query, "distance").map(str::parse) {
+                None => self.send(query, ArrowMessage::MoveForward(self.distance)),
+                Some(Ok(distance)) => self.send(query, ArrowMessage::MoveForward(distance)),
+                Some(Err(_)) => Reply::text(400, "distance must be a whole number of pixels"),
+            },
+            (Method::Post, "/goto") => match (number(query, "x"), number(query, "y")) {
+                (Ok(x), Ok(y)) => self.send(query, ArrowMessage::MoveTo { x, y }),
+                (Err(e), _) | (_, Err(e)) => Reply::text(400, e),
+            },
+            (Method::Post, "/face") => match param(query, "heading").map(str::parse) {
+                Some(Ok(heading)) => self.send(query, ArrowMessage::Face(heading)),
+                Some(Err(e)) => Reply::text(400, e),
+                None => Reply::text(400, "heading is missing"),
+            },
+            (Method::Post, "/radius") => match number(query, "radius") {
+                Ok(radius) => self.send(query, ArrowMessage::SetRadius(radius)),
+                Err(e) => Reply::text(400, e),
+            },
+            (Method::Post, "/reset") => self.send(query, ArrowMessage::Reset),
+            (Method::Get, "/state") => self.state(),
+            (Method::Get, "/frame.png") => self.frame(),
+            (_, "/rotate")
+            | (_, "/move")
+            | (_, "/goto")
+            | (_, "/face")
+            | (_, "/radius")
+            | (_, "/reset")
+            | (_, "/state")
+            | (_, "/frame.png") => Reply::text(405, "method not allowed"),
+            _ => Reply::text(404, "not found"),
+        }
+    }
+
+    /// Queues `message` for the arrow named in `query`, if there is one
+    fn send(&self, query: &str, message: ArrowMessage) -> Reply {
+        let message = match param(query, "arrow").map(str::parse::<ArrowId>) {
+            None => message,
+            Some(Ok(id)) => message.to(id),
+            Some(Err(_)) => return Reply::text(400, "arrow must be an arrow's id"),
+        };
+        if let Err(e) = self.scene.lock().unwrap().check(&message) {
+            return Reply::text(400, e);
+        }
+        match self.tx.send(message) {
+            Ok(()) => Reply::text(202, "queued"),
+            Err(e) => Reply::text(503, Error::from(e).to_string()),
+        }
+    }
+
+    fn state(&self) -> Reply {
+        let positions = ScenePosition::of(&self.scene.lock().unwrap());
+        Reply {
+            status: 200,
+            content_type: "application/json",
+            body: serde_json::to_vec(&positions).expect("positions serialize"),
+        }
+    }
+
+    fn frame(&self) -> Reply {
+        let shown = match self.shown.lock().unwrap().clone() {
+            Some(shown) => shown,
+            None => return Reply::text(503, "nothing has been drawn yet"),
+        };
+        let mut body = Vec::new();
+        match frame::write_png(
+            &mut body,
+            self.size,
+            self.orientation,
+            &shown.black,
+            &shown.red,
+        ) {
+            Ok(()) => Reply {
+                status: 200,
+                content_type: "image/png",
+                body,
+            },
+            Err(e) => Reply::text(500, format!("could not encode frame: {}", e)),
+        }
+    }
+}
+
+/// Value of `name` in a query string like `distance=20&direction=left`
+fn param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|&(key, _)| key == name)
+        .map(|(_, value)| value)
+}
+
+/// Whole number of pixels given as `name` in a query string
+fn number(query: &str, name: &str) -> std::result::Result<i32, String> {
+    let value = param(query, name).ok_or_else(|| format!("{} is missing", name))?;
+    value
+        .parse()
+        .map_err(|_| format!("{} must be a whole number of pixels, got '{}'", name, value))
+}
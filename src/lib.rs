@@ -0,0 +1,18 @@
+pub mod app;
+pub mod arrow;
+pub mod backend;
+pub mod button;
+pub mod cli;
+pub mod config;
+pub mod error;
+pub mod frame;
+pub mod http;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+pub mod panel;
+pub mod scene;
+pub mod script;
+pub mod state;
+pub mod trail;
+
+pub use error::{Error, Result};
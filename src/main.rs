@@ -1,229 +1,225 @@
-use embedded_graphics::{
-    geometry::Point,
-    prelude::*,
-    primitives::{PrimitiveStyle, Rectangle, Triangle},
-};
-use epd_waveshare::{
-    color::Black,
-    epd2in7b::{Display2in7b, Epd2in7b},
-    graphics::{Display, DisplayRotation},
-    prelude::*,
+#[cfg(feature = "mqtt")]
+use eink_arrow::mqtt::Mqtt;
+use eink_arrow::{
+    app::App,
+    arrow::ArrowMessage,
+    backend::{ChipSelect, EpdBackend},
+    button,
+    cli::Args,
+    config::{Config, CsPin},
+    http::HttpApi,
+    script,
+    state::SceneState,
+    Error, Result,
 };
 use linux_embedded_hal::{
     spidev::{self, SpidevOptions},
-    sysfs_gpio::Direction,
-    Delay, Pin, Spidev,
+    Spidev,
 };
-use rppal::gpio::Gpio;
-use rppal::gpio::Level;
-use rppal::gpio::Trigger;
-use std::sync::{mpsc, Arc, Mutex};
-
-struct Arrow {
-    pub x: i32,
-    pub y: i32,
-    pub radius: i32,
-    pub rotation: DisplayRotation,
-}
-
-impl Arrow {
-    fn new(radius: i32) -> Self {
-        Self {
-            radius,
-            x: radius,
-            y: radius,
-            rotation: DisplayRotation::Rotate0,
-        }
-    }
+use rppal::gpio::{Gpio, InputPin, OutputPin};
+use signal_hook::{
+    consts::{SIGINT, SIGTERM},
+    flag,
+    iterator::Signals,
+};
+use std::io::{self, BufReader};
+use std::process;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread;
 
-    fn draw(&self, display: &mut Display2in7b) {
-        display.clear_buffer(Color::White);
+// activate spi, gpio in raspi-config
+// runs as any user in the gpio and spi groups, through /dev/gpiomem and
+// /dev/spidev0.0
+
+// Pins go back to how they were found when dropped
+fn output(gpio: &Gpio, name: &'static str, pin: u8) -> Result<OutputPin> {
+    let mut output = gpio
+        .get(pin)
+        .map_err(|source| Error::Gpio { name, pin, source })?
+        .into_output();
+    output.set_high();
+    Ok(output)
+}
 
-        let rect_size = Size::new(self.radius as u32, self.radius as u32);
-        let (rectangle, triangle) = match self.rotation {
-            DisplayRotation::Rotate0 => (
-                Rectangle::new(
-                    Point::new(self.x - (self.radius / 2), self.y - self.radius),
-                    rect_size,
-                ),
-                Triangle::new(
-                    Point::new(self.x - self.radius, self.y),
-                    Point::new(self.x, self.y + self.radius),
-                    Point::new(self.x + self.radius, self.y),
-                ),
-            ),
-            DisplayRotation::Rotate90 => (
-                Rectangle::new(Point::new(self.x, self.y - (self.radius / 2)), rect_size),
-                Triangle::new(
-                    Point::new(self.x, self.y - self.radius),
-                    Point::new(self.x - self.radius, self.y),
-                    Point::new(self.x, self.y + self.radius),
-                ),
-            ),
-            DisplayRotation::Rotate180 => (
-                Rectangle::new(Point::new(self.x - (self.radius / 2), self.y), rect_size),
-                Triangle::new(
-                    Point::new(self.x - self.radius, self.y),
-                    Point::new(self.x, self.y - self.radius),
-                    Point::new(self.x + self.radius, self.y),
-                ),
-            ),
-            DisplayRotation::Rotate270 => (
-                Rectangle::new(
-                    Point::new(self.x - self.radius, self.y - (self.radius / 2)),
-                    rect_size,
-                ),
-                Triangle::new(
-                    Point::new(self.x, self.y - self.radius),
-                    Point::new(self.x + self.radius, self.y),
-                    Point::new(self.x, self.y + self.radius),
-                ),
-            ),
-        };
-        let _ = rectangle
-            .into_styled(PrimitiveStyle::with_fill(Black))
-            .draw(display);
-        let _ = triangle
-            .into_styled(PrimitiveStyle::with_fill(Black))
-            .draw(display);
-    }
+fn input(gpio: &Gpio, name: &'static str, pin: u8) -> Result<InputPin> {
+    gpio.get(pin)
+        .map(|pin| pin.into_input())
+        .map_err(|source| Error::Gpio { name, pin, source })
+}
 
-    fn rotate(&mut self) {
-        self.rotation = match self.rotation {
-            DisplayRotation::Rotate0 => DisplayRotation::Rotate90,
-            DisplayRotation::Rotate90 => DisplayRotation::Rotate180,
-            DisplayRotation::Rotate180 => DisplayRotation::Rotate270,
-            DisplayRotation::Rotate270 => DisplayRotation::Rotate0,
-        }
+fn run() -> Result<()> {
+    let args = Args::from_env()?;
+    if args.print_default_config {
+        print!("{}", Config::default_toml(args.profile.unwrap_or_default()));
+        return Ok(());
     }
-
-    fn move_forward(&mut self, distance: i32) {
-        match self.rotation {
-            DisplayRotation::Rotate0 => self.y += distance,
-            DisplayRotation::Rotate90 => self.x -= distance,
-            DisplayRotation::Rotate180 => self.y -= distance,
-            DisplayRotation::Rotate270 => self.x += distance,
+    let config = Config::load(&args)?;
+    let script = match &args.script {
+        Some(path) => Some(script::load(path, config.arrow.move_distance)?),
+        None => None,
+    };
+
+    let mut scene = config.scene();
+    let state_error = |source| Error::State {
+        path: config.state.path.clone(),
+        source,
+    };
+    if config.state.enabled && !args.reset_state {
+        if let Some(state) = SceneState::load(&config.state.path).map_err(state_error)? {
+            match state.restore(&mut scene) {
+                Ok(()) => println!("Restored arrows from {}", config.state.path.display()),
+                Err(e) => eprintln!(
+                    "error: could not restore all of {}, {}",
+                    config.state.path.display(),
+                    e
+                ),
+            }
         }
     }
-}
 
-#[derive(Copy, Clone, Debug)]
-enum ArrowMessage {
-    Rotate,
-    MoveForward(i32),
-}
-
-// activate spi, gpio in raspi-config
-// needs to be run with sudo because of some sysfs_gpio permission problems and follow-up timing problems
-// see https://github.com/rust-embedded/rust-sysfs-gpio/issues/5 and follow-up issues
-// https://github.com/rust-embedded/rust-sysfs-gpio/issues/24
-// https://github.com/golemparts/rppal/issues/41
-
-fn main() -> Result<(), std::io::Error> {
     // Configure SPI
-    let mut spi = Spidev::open("/dev/spidev0.0").expect("spidev directory");
+    let spi_error = |source| Error::Spi {
+        device: config.spi.device.clone(),
+        source,
+    };
+    let mut spi = Spidev::open(&config.spi.device).map_err(spi_error)?;
     let options = SpidevOptions::new()
         .bits_per_word(8)
-        .max_speed_hz(4_000_000)
+        .max_speed_hz(config.spi.speed_hz)
         .mode(spidev::SpiModeFlags::SPI_MODE_0)
         .build();
-    spi.configure(&options).expect("spi configuration");
-
-    // Configure Digital I/O Pin to be used as Chip Select for SPI
-    let cs = Pin::new(5); //BCM7 CE0
-    cs.export().expect("cs export");
-    while !cs.is_exported() {}
-    cs.set_direction(Direction::Out).expect("CS Direction");
-    cs.set_value(1).expect("CS Value set to 1");
-
-    let busy = Pin::new(19); //pin 29
-    busy.export().expect("busy export");
-    while !busy.is_exported() {}
-    busy.set_direction(Direction::In).expect("busy Direction");
-
-    let dc = Pin::new(6); //pin 31 //bcm6
-    dc.export().expect("dc export");
-    while !dc.is_exported() {}
-    dc.set_direction(Direction::Out).expect("dc Direction");
-    dc.set_value(1).expect("dc Value set to 1");
-
-    let rst = Pin::new(13); //pin 36 //bcm16
-    rst.export().expect("rst export");
-    while !rst.is_exported() {}
-    rst.set_direction(Direction::Out).expect("rst Direction");
-    rst.set_value(1).expect("rst Value set to 1");
-
-    let mut delay = Delay {};
-
-    let mut epd2in7b =
-        Epd2in7b::new(&mut spi, cs, busy, dc, rst, &mut delay).expect("eink initalize error");
-    println!("Initialized");
-
-    let mut display = Display2in7b::default();
-    let mut arrow = Arrow::new(20);
-
-    display.clear_buffer(Color::White);
-    epd2in7b.clear_frame(&mut spi, &mut delay)?;
-
-    arrow.draw(&mut display);
-
-    epd2in7b.update_frame(&mut spi, display.buffer(), &mut delay)?;
-    epd2in7b
-        .display_frame(&mut spi, &mut delay)
-        .expect("displaying");
-
-    let gpio = Gpio::new().expect("Gpio new");
-    // closest to ethernet
-    let move_button = gpio.get(20).expect("btn 1");
-    // furthest from output
-    let rotate_button = gpio.get(21).expect("btn 2");
-
-    let mut move_button_pin = move_button.into_input_pullup();
-    let mut rotate_button_pin = rotate_button.into_input_pullup();
-
-    let arrow_mutex = Arc::new(Mutex::new(arrow));
-
+    spi.configure(&options).map_err(spi_error)?;
+
+    let gpio = Gpio::new().map_err(|source| Error::Input {
+        context: "could not open GPIO".into(),
+        source,
+    })?;
+
+    // Configure the panel's control pins, CS included unless the SPI
+    // controller drives it
+    let cs = match config.pins.cs {
+        CsPin::Gpio(pin) => ChipSelect::Gpio(output(&gpio, "cs", pin)?),
+        CsPin::Hardware => ChipSelect::Hardware,
+    };
+    let busy = input(&gpio, "busy", config.pins.busy)?;
+    let dc = output(&gpio, "dc", config.pins.dc)?;
+    let rst = output(&gpio, "rst", config.pins.rst)?;
+
+    // Shut down through the message loop so the panel is never left powered
+    // on, which can damage it, and any refresh in progress gets to finish.
+    // A second signal exits straight away, in case that is what's stuck.
     let (tx, rx) = mpsc::channel();
-    let rotate_tx = tx.clone();
+    let shutdown_tx = tx.clone();
+    let signalled = Arc::new(AtomicBool::new(false));
+    for &signal in &[SIGINT, SIGTERM] {
+        flag::register_conditional_shutdown(signal, 1, signalled.clone()).map_err(Error::Signal)?;
+        flag::register(signal, signalled.clone()).map_err(Error::Signal)?;
+    }
+    let mut signals = Signals::new([SIGINT, SIGTERM]).map_err(Error::Signal)?;
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            println!("Received signal {}, shutting down", signal);
+            let _ = shutdown_tx.send(ArrowMessage::Shutdown);
+        }
+    });
+
+    let mut backend = EpdBackend::new(config.display.model, spi, cs, busy, dc, rst);
+    backend.set_busy_timeout(config.display.busy_timeout(), config.display.busy_retries);
+    let mut app = App::new(backend, scene);
+    app.set_orientation(config.display.orientation);
+    app.set_settle(config.display.settle());
+    app.set_full_refresh_every(config.display.full_refresh_every);
+    if config.state.enabled {
+        app.set_state_file(config.state.path.clone());
+        app.set_frame_file(config.state.frame_path.clone());
+    }
+    #[cfg(feature = "mqtt")]
+    {
+        if config.mqtt.enabled {
+            let mqtt = Mqtt::start(&config.mqtt, tx.clone(), config.arrow.move_distance);
+            app.on_refresh(move |scene| mqtt.publish(scene));
+        }
+    }
+    app.start()?;
+    println!("Initialized");
 
-    move_button_pin
-        .set_async_interrupt(Trigger::FallingEdge, move |level: Level| {
-            println!("Btn 1 pushed: {}", level);
-            if let Level::Low = level {
-                tx.send(ArrowMessage::MoveForward(100)).unwrap();
-            }
-        })
-        .unwrap();
-    rotate_button_pin
-        .set_async_interrupt(Trigger::FallingEdge, move |level: Level| {
-            println!("Btn 2 pushed: {}", level);
-            if let Level::Low = level {
-                rotate_tx.send(ArrowMessage::Rotate).unwrap();
+    let distance = config.arrow.move_distance;
+    // Interrupts stop when the pins are dropped, so keep them until shutdown
+    let mut button_pins = Vec::new();
+    for button in config.buttons.iter().cloned() {
+        let label = button.label();
+        let mut pin = gpio
+            .get(button.pin)
+            .map(|pin| pin.into_input_pullup())
+            .map_err(|source| Error::Input {
+                context: format!("could not set up button {}", label),
+                source,
+            })?;
+        let timing = button.timing(&config.input);
+        let button_tx = tx.clone();
+        let watch_error = |source| Error::Input {
+            context: format!("could not watch button {}", label),
+            source,
+        };
+        let press_label = label.clone();
+        button::watch(&mut pin, timing, move |press| {
+            println!("Button {}: {:?} press", press_label, press);
+            if let Some(message) = button.message(press, distance) {
+                send(&button_tx, message);
             }
         })
-        .unwrap();
+        .map_err(watch_error)?;
+        button_pins.push(pin);
+    }
+
+    if config.http.enabled {
+        let api = HttpApi::new(
+            tx.clone(),
+            app.scene(),
+            app.shown(),
+            config.display.model.size(),
+            config.display.orientation,
+            distance,
+        );
+        let address = api.spawn(&config.http.bind)?;
+        println!("Serving HTTP API on http://{}", address);
+    }
+
+    if let Some(commands) = script {
+        script::play(commands, tx.clone());
+    } else if args.stdin {
+        script::follow(BufReader::new(io::stdin()), distance, tx.clone());
+    }
 
     println!("Waiting for input");
 
-    for received in rx {
-        println!(
-            "button 1 (move): {}, button 2 (rotate): {}",
-            move_button_pin.read(),
-            rotate_button_pin.read()
-        );
-        let mut arrow = arrow_mutex.lock().unwrap();
-        match received {
-            ArrowMessage::MoveForward(distance) => arrow.move_forward(distance),
-            ArrowMessage::Rotate => arrow.rotate(),
-        }
-        arrow.draw(&mut display);
-        epd2in7b.update_frame(&mut spi, display.buffer(), &mut delay)?;
-        epd2in7b
-            .display_frame(&mut spi, &mut delay)
-            .expect("displaying");
+    let result = app.run(rx);
+    // A panel that stopped responding won't respond to sleep either
+    if let Err(Error::BusyTimeout { .. }) = result {
+        return result;
     }
 
-    // TODO: Handle interrupt
     println!("Finished, going to sleep");
-    epd2in7b.sleep(&mut spi, &mut delay)
+    let slept = app.sleep();
+    // Whatever stopped the run matters more than failing to sleep after it
+    if let (Err(_), Err(e)) = (&result, &slept) {
+        eprintln!("error: could not put the panel to sleep: {}", e);
+    }
+    result.and(slept)
+}
+
+// Button callbacks run on rppal's interrupt thread, so report failures there
+fn send(tx: &Sender<ArrowMessage>, message: ArrowMessage) {
+    if let Err(e) = tx.send(message) {
+        eprintln!("error: {}", Error::from(e));
+    }
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
 }
@@ -0,0 +1,202 @@
+use crate::arrow::ArrowMessage;
+use crate::config::MqttConfig;
+use crate::scene::Scene;
+use crate::state::ScenePosition;
+use rumqttc::{Client, Connection, Event, LastWill, MqttOptions, Packet, QoS};
+use std::str;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+
+// Longest wait between attempts to reach the broker
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+// Topics under the configured one that drive the arrow
+const COMMANDS: [&str; 6] = ["rotate", "move", "goto", "face", "radius", "reset"];
+
+/// Lets a home automation system drive the arrow through an MQTT broker,
+/// alongside the buttons. Under the configured topic:
+///
+/// - `<topic>/rotate` turns clockwise, or counter-clockwise with the payload
+///   `left`
+/// - `<topic>/move` moves forward, by the number of pixels in the payload if
+///   there is one
+/// - `<topic>/goto` jumps to the point in the payload, like `80 120`
+/// - `<topic>/face` turns to the heading in the payload, like `up` or `90`
+/// - `<topic>/radius` resizes the arrow to the number of pixels in the payload
+/// - `<topic>/reset` goes back to the starting position
+/// - `<topic>/arrow/<id>/<command>` sends any of those to the arrow with that
+///   id, where plain `<topic>/<command>` goes to the selected one
+/// - `<topic>/state` is published with every arrow's position and heading as
+///   JSON after each refresh, retained
+/// - `<topic>/availability` is retained as `online` while connected, with the
+///   broker setting it to `offline` when the connection drops
+pub struct Mqtt {
+    client: Client,
+    topic: String,
+}
+
+impl Mqtt {
+    /// Connects in the background, sending commands to `tx`. A lost
+    /// connection is retried with increasing delays for as long as it takes.
+    pub fn start(config: &MqttConfig, tx: Sender<ArrowMessage>, distance: i32) -> Self {
+        let availability = format!("{}/availability", config.topic);
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        options.set_last_will(LastWill::new(
+            &availability,
+            "offline",
+            QoS::AtLeastOnce,
+            true,
+        ));
+        if let Some(username) = &config.username {
+            let password = config.password.clone().unwrap_or_default();
+            options.set_credentials(username, password);
+        }
+        let (client, connection) = Client::new(options, 10);
+        let listener = Listener {
+            client: client.clone(),
+            topic: config.topic.clone(),
+            tx,
+            distance,
+        };
+        thread::spawn(move || listener.run(connection));
+        Self {
+            client,
+            topic: config.topic.clone(),
+        }
+    }
+
+    /// Publishes where the arrows are now, meant for `App::on_refresh`
+    pub fn publish(&self, scene: &Scene) {
+        let payload = serde_json::to_vec(&ScenePosition::of(scene)).expect("positions serialize");
+        let topic = format!("{}/state", self.topic);
+        // Never hold up the panel waiting on the broker
+        if let Err(e) = self
+            .client
+            .try_publish(topic, QoS::AtLeastOnce, true, payload)
+        {
+            eprintln!("error: could not publish arrow state: {}", e);
+        }
+    }
+}
+
+struct Listener {
+    client: Client,
+    topic: String,
+    tx: Sender<ArrowMessage>,
+    distance: i32,
+}
+
+impl Listener {
+    fn run(self, mut connection: Connection) {
+        let mut backoff = Duration::from_secs(1);
+        for event in connection.iter() {
+            match event {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    println!("Connected to MQTT broker");
+                    backoff = Duration::from_secs(1);
+                    self.announce();
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    let command = match publish
+                        .topic
+                        .strip_prefix(&self.topic)
+                        .and_then(|command| command.strip_prefix('/'))
+                    {
+                        Some(command) => command,
+                        None => continue,
+                    };
+                    match message(command, &publish.payload, self.distance) {
+                        Ok(message) => {
+                            if self.tx.send(message).is_err() {
+                                return;
+                            }
+                        }
+                        Err(e) => eprintln!("error: ignoring MQTT {}: {}", publish.topic, e),
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!(
+                        "error: MQTT connection failed: {}, retrying in {}s",
+                        e,
+                        backoff.as_secs()
+                    );
+                    thread::sleep(backoff);
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+            }
+        }
+    }
+
+    // Subscriptions don't outlive a clean session, so they're made again on
+    // every connect
+    fn announce(&self) {
+        let mut requests: Vec<_> = COMMANDS
+            .iter()
+            .flat_map(|command| {
+                [
+                    format!("{}/{}", self.topic, command),
+                    format!("{}/arrow/+/{}", self.topic, command),
+                ]
+            })
+            .map(|topic| self.client.try_subscribe(topic, QoS::AtLeastOnce))
+            .collect();
+        requests.push(self.client.try_publish(
+            format!("{}/availability", self.topic),
+            QoS::AtLeastOnce,
+            true,
+            "online",
+        ));
+        for result in requests.iter() {
+            if let Err(e) = result {
+                eprintln!("error: could not set up MQTT topics: {}", e);
+            }
+        }
+    }
+}
+
+/// The message for a `command` topic under the configured one, like `move`
+/// or `arrow/2/move`, with the given payload
+pub fn message(command: &str, payload: &[u8], distance: i32) -> Result<ArrowMessage, String> {
+    if let Some(command) = command.strip_prefix("arrow/") {
+        let (id, command) = command
+            .split_once('/')
+            .ok_or_else(|| format!("expected arrow/<id>/<command>, got 'arrow/{}'", command))?;
+        let id = id
+            .parse()
+            .map_err(|_| format!("'{}' is not an arrow's id", id))?;
+        return message(command, payload, distance).map(|message| message.to(id));
+    }
+    let payload = str::from_utf8(payload)
+        .map_err(|_| "payload is not text".to_string())?
+        .trim();
+    match (command, payload) {
+        ("rotate", "") | ("rotate", "right") => Ok(ArrowMessage::Rotate),
+        ("rotate", "left") => Ok(ArrowMessage::RotateLeft),
+        ("rotate", direction) => Err(format!(
+            "unknown direction '{}', expected left or right",
+            direction
+        )),
+        ("move", "") => Ok(ArrowMessage::MoveForward(distance)),
+        ("move", distance) => pixels(distance).map(ArrowMessage::MoveForward),
+        ("goto", point) => match point.split_whitespace().collect::<Vec<_>>()[..] {
+            [x, y] => Ok(ArrowMessage::MoveTo {
+                x: pixels(x)?,
+                y: pixels(y)?,
+            }),
+            _ => Err(format!("expected a point like '80 120', got '{}'", point)),
+        },
+        ("face", heading) => heading.parse().map(ArrowMessage::Face),
+        ("radius", radius) => pixels(radius).map(ArrowMessage::SetRadius),
+        ("reset", _) => Ok(ArrowMessage::Reset),
+        (command, _) => Err(format!("unknown command '{}'", command)),
+    }
+}
+
+fn pixels(value: &str) -> Result<i32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("'{}' is not a whole number of pixels", value))
+}
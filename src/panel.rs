@@ -0,0 +1,131 @@
+use embedded_graphics::geometry::{Point, Size};
+use epd_waveshare::{epd1in54, epd2in13_v2, epd2in7b, epd4in2};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// The Waveshare panels the arrow can be drawn on
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Model {
+    /// 2.7" black, white and red
+    #[default]
+    Epd2in7b,
+    /// 2.13" black and white, version 2
+    Epd2in13V2,
+    /// 4.2" black and white
+    Epd4in2,
+    /// 1.54" black and white
+    Epd1in54,
+}
+
+impl Model {
+    /// Size in the panel's native orientation
+    pub fn size(self) -> Size {
+        match self {
+            Model::Epd2in7b => Size::new(epd2in7b::WIDTH, epd2in7b::HEIGHT),
+            Model::Epd2in13V2 => Size::new(epd2in13_v2::WIDTH, epd2in13_v2::HEIGHT),
+            Model::Epd4in2 => Size::new(epd4in2::WIDTH, epd4in2::HEIGHT),
+            Model::Epd1in54 => Size::new(epd1in54::WIDTH, epd1in54::HEIGHT),
+        }
+    }
+
+    /// Whether the panel can show red, otherwise red is drawn in black
+    pub fn has_red(self) -> bool {
+        self == Model::Epd2in7b
+    }
+
+    /// Whether the panel can refresh part of itself
+    pub fn supports_partial(self) -> bool {
+        self != Model::Epd2in7b
+    }
+
+    /// Arrow radius that looks the same on this panel as 20 does on the 2.7"
+    pub fn default_radius(self) -> i32 {
+        let size = self.size();
+        let reference = epd2in7b::WIDTH.min(epd2in7b::HEIGHT);
+        (size.width.min(size.height) * 20 / reference) as i32
+    }
+}
+
+impl FromStr for Model {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "epd2in7b" => Ok(Model::Epd2in7b),
+            "epd2in13_v2" => Ok(Model::Epd2in13V2),
+            "epd4in2" => Ok(Model::Epd4in2),
+            "epd1in54" => Ok(Model::Epd1in54),
+            _ => Err(format!(
+                "unknown panel '{}', expected epd2in7b, epd2in13_v2, epd4in2 or epd1in54",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Model::Epd2in7b => "epd2in7b",
+            Model::Epd2in13V2 => "epd2in13_v2",
+            Model::Epd4in2 => "epd4in2",
+            Model::Epd1in54 => "epd1in54",
+        })
+    }
+}
+
+/// Which way up the panel is mounted, turned clockwise from its native
+/// portrait orientation. The arrow is drawn and moved the way it is seen, so
+/// up is always the top of the panel as mounted.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Orientation {
+    #[default]
+    Portrait,
+    Landscape,
+    PortraitFlipped,
+    LandscapeFlipped,
+}
+
+impl Orientation {
+    /// Size of a panel that is `native` in size, as seen mounted this way
+    pub fn size(self, native: Size) -> Size {
+        match self {
+            Orientation::Portrait | Orientation::PortraitFlipped => native,
+            Orientation::Landscape | Orientation::LandscapeFlipped => {
+                Size::new(native.height, native.width)
+            }
+        }
+    }
+
+    /// Where the pixel seen at `point` is on a panel that is `native` in
+    /// size, turning the same way as the drivers' `DisplayRotation`
+    pub fn to_native(self, point: Point, native: Size) -> Point {
+        let (width, height) = (native.width as i32, native.height as i32);
+        match self {
+            Orientation::Portrait => point,
+            Orientation::Landscape => Point::new(width - 1 - point.y, point.x),
+            Orientation::PortraitFlipped => Point::new(width - 1 - point.x, height - 1 - point.y),
+            Orientation::LandscapeFlipped => Point::new(point.y, height - 1 - point.x),
+        }
+    }
+}
+
+impl FromStr for Orientation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "portrait" => Ok(Orientation::Portrait),
+            "landscape" => Ok(Orientation::Landscape),
+            "portrait-flipped" => Ok(Orientation::PortraitFlipped),
+            "landscape-flipped" => Ok(Orientation::LandscapeFlipped),
+            _ => Err(format!(
+                "unknown orientation '{}', expected portrait, landscape, portrait-flipped or landscape-flipped",
+                s
+            )),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use crate::arrow::{Arrow, ArrowId, ArrowMessage};
+use crate::frame::Frame;
+
+/// Every arrow on the panel, one of which is selected to receive messages
+/// that don't name an arrow
+pub struct Scene {
+    arrows: Vec<Arrow>,
+    selected: usize,
+}
+
+impl Scene {
+    /// A scene with the first of `arrows` selected. There has to be at least
+    /// one.
+    pub fn new(arrows: Vec<Arrow>) -> Self {
+        assert!(!arrows.is_empty(), "a scene needs at least one arrow");
+        Self {
+            arrows,
+            selected: 0,
+        }
+    }
+
+    pub fn arrows(&self) -> &[Arrow] {
+        &self.arrows
+    }
+
+    pub fn arrows_mut(&mut self) -> &mut [Arrow] {
+        &mut self.arrows
+    }
+
+    pub fn arrow(&self, id: ArrowId) -> Option<&Arrow> {
+        self.arrows.iter().find(|arrow| arrow.id == id)
+    }
+
+    /// The arrow messages without a target go to
+    pub fn selected(&self) -> &Arrow {
+        &self.arrows[self.selected]
+    }
+
+    /// Checks a message the way `Arrow::check` does, against the arrow it's
+    /// meant for
+    pub fn check(&self, message: &ArrowMessage) -> Result<(), String> {
+        match message {
+            ArrowMessage::To(id, message) => match self.arrow(*id) {
+                Some(arrow) => arrow.check(message),
+                None => Err(format!("there is no arrow {}", id)),
+            },
+            message => self.selected().check(message),
+        }
+    }
+
+    /// Applies a message to the arrow it's meant for, returning whether that
+    /// changes what's drawn
+    pub fn apply(&mut self, message: ArrowMessage) -> bool {
+        match message {
+            ArrowMessage::To(id, message) => self
+                .arrows
+                .iter_mut()
+                .find(|arrow| arrow.id == id)
+                .is_some_and(|arrow| arrow.apply(*message)),
+            ArrowMessage::SelectNext => {
+                self.selected = (self.selected + 1) % self.arrows.len();
+                false
+            }
+            message => self.arrows[self.selected].apply(message),
+        }
+    }
+
+    /// Draws every arrow, with all the trails underneath all the arrows
+    pub fn draw(&self, frame: &mut Frame) {
+        frame.clear();
+        for arrow in self.arrows.iter() {
+            arrow.draw_trail(frame);
+        }
+        for arrow in self.arrows.iter() {
+            arrow.draw_over(frame);
+        }
+    }
+}
+
+impl From<Arrow> for Scene {
+    fn from(arrow: Arrow) -> Self {
+        Self::new(vec![arrow])
+    }
+}
@@ -0,0 +1,182 @@
+use crate::arrow::{ArrowId, ArrowMessage};
+use crate::error::{Error, Result};
+use std::fs;
+use std::io::BufRead;
+use std::path::Path;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+
+/// One line of a script, read from a file with `--script` or typed on stdin
+/// with `--stdin`:
+///
+/// - `rotate [left|right]` turns, clockwise unless told otherwise
+/// - `move [pixels]` moves forward, by the configured distance if not given
+/// - `goto <x> <y>` jumps straight to a point on the panel
+/// - `face <heading>` turns to `up`, `down`, `left`, `right`, a compass point
+///   or degrees
+/// - `radius <pixels>` resizes the arrow
+/// - `reset` goes back to the starting position
+/// - `refresh` redraws the whole panel
+/// - `sleep <n>[ms|s|m]` waits before the next line, in seconds by default
+///
+/// Any of them but `refresh` and `sleep` can start with `arrow <id>` to send it to that
+/// arrow rather than the selected one, like `arrow 2 move 30`. Blank lines
+/// and anything after a `#` are ignored.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Send(ArrowMessage),
+    Sleep(Duration),
+}
+
+impl Command {
+    /// The command on `line`, if it has one. `distance` is how far a `move`
+    /// without one goes.
+    pub fn parse(line: &str, distance: i32) -> std::result::Result<Option<Self>, String> {
+        let line = line.split('#').next().unwrap_or_default();
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => Ok(None),
+            ["arrow", id, command @ ..] if !command.is_empty() => {
+                let id: ArrowId = id
+                    .parse()
+                    .map_err(|_| format!("'{}' is not an arrow's id", id))?;
+                match Self::parse_words(command, distance)? {
+                    Command::Send(ArrowMessage::Refresh) | Command::Sleep(_) => {
+                        Err(format!("{} can't be sent to an arrow", command[0]))
+                    }
+                    Command::Send(message) => Ok(Some(Command::Send(message.to(id)))),
+                }
+            }
+            words => Self::parse_words(words, distance).map(Some),
+        }
+    }
+
+    fn parse_words(words: &[&str], distance: i32) -> std::result::Result<Self, String> {
+        let message = match words {
+            ["rotate"] | ["rotate", "right"] => ArrowMessage::Rotate,
+            ["rotate", "left"] => ArrowMessage::RotateLeft,
+            ["rotate", direction] => {
+                return Err(format!(
+                    "unknown direction '{}', expected left or right",
+                    direction
+                ))
+            }
+            ["move"] => ArrowMessage::MoveForward(distance),
+            ["move", distance] => ArrowMessage::MoveForward(pixels(distance)?),
+            ["goto", x, y] => ArrowMessage::MoveTo {
+                x: pixels(x)?,
+                y: pixels(y)?,
+            },
+            ["face", heading] => ArrowMessage::Face(heading.parse()?),
+            ["radius", radius] => ArrowMessage::SetRadius(pixels(radius)?),
+            ["reset"] => ArrowMessage::Reset,
+            ["refresh"] => ArrowMessage::Refresh,
+            ["sleep", duration] => return duration_of(duration).map(Command::Sleep),
+            [] => return Err("missing command".to_string()),
+            [command, ..] => {
+                return Err(match *command {
+                    "rotate" | "move" | "goto" | "face" | "radius" | "reset" | "refresh"
+                    | "sleep" | "arrow" => {
+                        format!("wrong number of arguments to {}", command)
+                    }
+                    _ => format!("unknown command '{}'", command),
+                })
+            }
+        };
+        Ok(Command::Send(message))
+    }
+}
+
+fn pixels(value: &str) -> std::result::Result<i32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("'{}' is not a whole number of pixels", value))
+}
+
+fn duration_of(value: &str) -> std::result::Result<Duration, String> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let scale = match unit {
+        "ms" => 0.001,
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        _ => return Err(format!("unknown unit '{}', expected ms, s or m", unit)),
+    };
+    number
+        .parse::<f64>()
+        .ok()
+        .and_then(|n| Duration::try_from_secs_f64(n * scale).ok())
+        .ok_or_else(|| format!("'{}' is not a duration", value))
+}
+
+/// Reads every command in the script at `path`, so a mistake anywhere in it
+/// is caught before the panel is touched
+pub fn load(path: &Path, distance: i32) -> Result<Vec<Command>> {
+    let script_error = |line, message| Error::Script {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+    let text = fs::read_to_string(path).map_err(|e| script_error(None, e.to_string()))?;
+    let mut commands = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        if let Some(command) =
+            Command::parse(line, distance).map_err(|e| script_error(Some(number + 1), e))?
+        {
+            commands.push(command);
+        }
+    }
+    Ok(commands)
+}
+
+/// Sends `commands` to `tx` in the background, in order, then shuts down
+pub fn play(commands: Vec<Command>, tx: Sender<ArrowMessage>) {
+    thread::spawn(move || {
+        for command in commands {
+            if !run(command, &tx) {
+                return;
+            }
+        }
+        let _ = tx.send(ArrowMessage::Shutdown);
+    });
+}
+
+/// Runs commands from `input` as each line arrives, then shuts down once it
+/// ends. Bad lines are reported and skipped.
+pub fn follow<R: BufRead + Send + 'static>(input: R, distance: i32, tx: Sender<ArrowMessage>) {
+    thread::spawn(move || {
+        for (number, line) in input.lines().enumerate() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    eprintln!("error: could not read commands: {}", e);
+                    break;
+                }
+            };
+            match Command::parse(&line, distance) {
+                Ok(Some(command)) => {
+                    if !run(command, &tx) {
+                        return;
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("error: ignoring line {}: {}", number + 1, e),
+            }
+        }
+        let _ = tx.send(ArrowMessage::Shutdown);
+    });
+}
+
+// False once the event loop has stopped
+fn run(command: Command, tx: &Sender<ArrowMessage>) -> bool {
+    match command {
+        Command::Send(message) => tx.send(message).is_ok(),
+        Command::Sleep(duration) => {
+            thread::sleep(duration);
+            true
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use crate::arrow::{Arrow, ArrowId, ArrowMessage, Heading};
+use crate::frame::Frame;
+use crate::scene::Scene;
+use crate::trail::{Pose, TrailStep};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Everything needed to put the arrow back where the panel last showed it
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ArrowState {
+    /// Left out by versions that only had one arrow
+    #[serde(default = "first_id")]
+    pub id: ArrowId,
+    pub x: i32,
+    pub y: i32,
+    /// Degrees clockwise from facing down
+    pub heading: f32,
+    pub radius: i32,
+    #[serde(default)]
+    pub trail: Vec<SavedStep>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SavedStep {
+    pub x: i32,
+    pub y: i32,
+    pub heading: f32,
+    pub joined: bool,
+}
+
+impl ArrowState {
+    pub fn of(arrow: &Arrow) -> Self {
+        Self {
+            id: arrow.id,
+            x: arrow.x,
+            y: arrow.y,
+            heading: arrow.heading.degrees(),
+            radius: arrow.radius,
+            trail: arrow
+                .trail
+                .steps()
+                .map(|step| SavedStep {
+                    x: step.pose.x,
+                    y: step.pose.y,
+                    heading: step.pose.heading.degrees(),
+                    joined: step.joined,
+                })
+                .collect(),
+        }
+    }
+
+    /// Moves the arrow to the saved position, keeping its other settings. A
+    /// saved radius that doesn't fit the panel leaves the arrow as it was.
+    pub fn restore(&self, arrow: &mut Arrow) -> Result<(), String> {
+        arrow
+            .check(&ArrowMessage::SetRadius(self.radius))
+            .map_err(|e| format!("saved {}", e))?;
+        arrow.x = self.x;
+        arrow.y = self.y;
+        arrow.heading = Heading::from_degrees_f32(self.heading);
+        arrow.trail.restore(self.trail.iter().map(|step| TrailStep {
+            pose: Pose {
+                x: step.x,
+                y: step.y,
+                heading: Heading::from_degrees_f32(step.heading),
+            },
+            joined: step.joined,
+        }));
+        // Also pulls the arrow back onto the panel, in case the panel
+        // changed since it was saved, without turning it
+        arrow.set_radius(self.radius);
+        Ok(())
+    }
+
+    /// Reads a saved state, or `None` if nothing has been saved yet
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        load_toml(path)
+    }
+
+    /// Replaces the saved state at `path` in one go
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        save_toml(path, self)
+    }
+}
+
+fn first_id() -> ArrowId {
+    1
+}
+
+/// The saved state of every arrow in a scene
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SceneState {
+    pub arrows: Vec<ArrowState>,
+}
+
+impl SceneState {
+    pub fn of(scene: &Scene) -> Self {
+        Self {
+            arrows: scene.arrows().iter().map(ArrowState::of).collect(),
+        }
+    }
+
+    /// Puts each arrow back where it was saved, matched up by id. Arrows
+    /// that weren't saved, or can't be restored, are left where they start,
+    /// with the first problem returned once the rest have been restored.
+    pub fn restore(&self, scene: &mut Scene) -> Result<(), String> {
+        let mut result = Ok(());
+        for arrow in scene.arrows_mut() {
+            if let Some(state) = self.arrows.iter().find(|state| state.id == arrow.id) {
+                let restored = state
+                    .restore(arrow)
+                    .map_err(|e| format!("arrow {}: {}", arrow.id, e));
+                result = result.and(restored);
+            }
+        }
+        result
+    }
+
+    /// Reads a saved scene, or `None` if nothing has been saved yet. A single
+    /// arrow saved with `ArrowState` loads as a scene of one.
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        let saved: toml::Value = match load_toml(path)? {
+            Some(saved) => saved,
+            None => return Ok(None),
+        };
+        let scene = if saved.get("arrows").is_some() {
+            saved.try_into()
+        } else {
+            saved.try_into().map(|arrow| Self {
+                arrows: vec![arrow],
+            })
+        };
+        scene.map(Some).map_err(invalid_data)
+    }
+
+    /// Replaces the saved state at `path` in one go, in the `ArrowState`
+    /// format when there's only one arrow
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        match self.arrows.as_slice() {
+            [arrow] => arrow.save(path),
+            _ => save_toml(path, self),
+        }
+    }
+}
+
+fn load_toml<T: DeserializeOwned>(path: &Path) -> io::Result<Option<T>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    toml::from_str(&contents).map(Some).map_err(invalid_data)
+}
+
+fn save_toml<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    let contents = toml::to_string(value).map_err(invalid_data)?;
+    write_atomically(path, contents.as_bytes())
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Where an arrow is and which way it faces, as reported to other programs
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Position {
+    pub id: ArrowId,
+    pub x: i32,
+    pub y: i32,
+    /// Degrees clockwise from facing down
+    pub heading: f32,
+}
+
+impl Position {
+    pub fn of(arrow: &Arrow) -> Self {
+        Self {
+            id: arrow.id,
+            x: arrow.x,
+            y: arrow.y,
+            heading: arrow.heading.degrees(),
+        }
+    }
+}
+
+/// Every arrow's position, and which one messages without an arrow go to
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ScenePosition {
+    pub selected: ArrowId,
+    pub arrows: Vec<Position>,
+}
+
+impl ScenePosition {
+    pub fn of(scene: &Scene) -> Self {
+        Self {
+            selected: scene.selected().id,
+            arrows: scene.arrows().iter().map(Position::of).collect(),
+        }
+    }
+}
+
+/// Saves both layers of the frame last pushed to the panel
+pub fn save_frame(path: &Path, frame: &Frame) -> io::Result<()> {
+    write_atomically(path, &[frame.black(), frame.red()].concat())
+}
+
+/// Whether `frame` is the one last saved with `save_frame`, and so still on
+/// the panel
+pub fn shows_frame(path: &Path, frame: &Frame) -> io::Result<bool> {
+    let saved = match fs::read(path) {
+        Ok(saved) => saved,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    let (black, red) = saved.split_at(saved.len() / 2);
+    Ok(black == frame.black() && red == frame.red())
+}
+
+/// Writes next to `path` first and then renames into place, so a power cut
+/// never leaves a half written file behind
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temporary = temporary_path(path);
+    let mut file = File::create(&temporary)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)
+}
+
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
@@ -0,0 +1,168 @@
+use crate::arrow::{Arrow, Heading, Part};
+use crate::frame::{Frame, Ink, Layer};
+use embedded_graphics::{
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{Line, PrimitiveStyle, Rectangle},
+};
+use epd_waveshare::color::Black;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+// Pixels between the dots of a trail line, and the size of each dot
+const DOT_SPACING: usize = 6;
+const DOT_SIZE: u32 = 2;
+
+/// Where the arrow was and which way it was facing
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Pose {
+    pub x: i32,
+    pub y: i32,
+    pub heading: Heading,
+}
+
+/// How earlier positions are drawn behind the arrow
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrailStyle {
+    /// Dotted lines along the path
+    #[default]
+    Dots,
+    /// A small faded arrow at each earlier position
+    Arrows,
+}
+
+/// A position on the trail
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TrailStep {
+    pub pose: Pose,
+    /// Whether a line connects this step to the one before it
+    pub joined: bool,
+}
+
+/// Bounded history of the positions the arrow has moved through
+#[derive(Clone, Debug, Default)]
+pub struct Trail {
+    /// Number of earlier positions kept, 0 to turn the trail off
+    pub length: usize,
+    pub style: TrailStyle,
+    pub ink: Ink,
+    // Oldest first, ending with the arrow's current position
+    steps: VecDeque<TrailStep>,
+}
+
+impl Trail {
+    pub fn new(length: usize, style: TrailStyle, ink: Ink) -> Self {
+        Self {
+            length,
+            style,
+            ink,
+            steps: VecDeque::new(),
+        }
+    }
+
+    /// Records a move from one position to another, `joined` if the path
+    /// between them should be drawn
+    pub fn record(&mut self, from: Pose, to: Pose, joined: bool) {
+        if self.length == 0 {
+            return;
+        }
+        // The arrow may have turned on the spot since it last moved
+        match self.steps.back_mut() {
+            Some(last) if (last.pose.x, last.pose.y) == (from.x, from.y) => last.pose = from,
+            _ => self.steps.push_back(TrailStep {
+                pose: from,
+                joined: false,
+            }),
+        }
+        self.steps.push_back(TrailStep { pose: to, joined });
+        self.truncate();
+    }
+
+    pub fn clear(&mut self) {
+        self.steps.clear();
+    }
+
+    /// Every step, oldest first, ending with the arrow's current position
+    pub fn steps(&self) -> impl Iterator<Item = TrailStep> + '_ {
+        self.steps.iter().copied()
+    }
+
+    /// Replaces the history with previously saved steps, keeping only as
+    /// many as `length` allows
+    pub fn restore<I: IntoIterator<Item = TrailStep>>(&mut self, steps: I) {
+        self.steps = steps.into_iter().collect();
+        self.truncate();
+    }
+
+    fn truncate(&mut self) {
+        let keep = if self.length == 0 { 0 } else { self.length + 1 };
+        while self.steps.len() > keep {
+            self.steps.pop_front();
+        }
+    }
+
+    /// Earlier positions, oldest first, not including the current one
+    pub fn poses(&self) -> impl Iterator<Item = Pose> + '_ {
+        let earlier = self.steps.len().saturating_sub(1);
+        self.steps.iter().take(earlier).map(|step| step.pose)
+    }
+
+    pub(crate) fn draw(&self, frame: &mut Frame, radius: i32) {
+        let layer = frame.layer_mut(self.ink);
+        match self.style {
+            TrailStyle::Dots => {
+                let dot = PrimitiveStyle::with_fill(Black);
+                let segments = self.steps.iter().zip(self.steps.iter().skip(1));
+                for (from, to) in segments.filter(|(_, to)| to.joined) {
+                    let line = Line::new(
+                        Point::new(from.pose.x, from.pose.y),
+                        Point::new(to.pose.x, to.pose.y),
+                    );
+                    for point in line.points().step_by(DOT_SPACING) {
+                        let _ = Rectangle::new(point, Size::new(DOT_SIZE, DOT_SIZE))
+                            .into_styled(dot)
+                            .draw(layer);
+                    }
+                }
+            }
+            TrailStyle::Arrows => {
+                let mut arrow = Arrow::new((radius / 2).max(2));
+                let mut faded = Faded(layer);
+                for pose in self.poses() {
+                    arrow.x = pose.x;
+                    arrow.y = pose.y;
+                    arrow.heading = pose.heading;
+                    arrow.draw_part(&mut faded, Part::Shaft);
+                    arrow.draw_part(&mut faded, Part::Head);
+                }
+            }
+        }
+    }
+}
+
+/// Only lets through every other pixel in a checkerboard, the closest a
+/// two-color panel gets to drawing something lighter
+struct Faded<'a>(&'a mut Layer);
+
+impl DrawTarget for Faded<'_> {
+    type Color = BinaryColor;
+    type Error = <Layer as DrawTarget>::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.0.draw_iter(
+            pixels
+                .into_iter()
+                .filter(|Pixel(point, _)| (point.x + point.y).rem_euclid(2) == 0),
+        )
+    }
+}
+
+impl OriginDimensions for Faded<'_> {
+    fn size(&self) -> Size {
+        self.0.size()
+    }
+}
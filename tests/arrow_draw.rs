@@ -0,0 +1,182 @@
+//! Snapshot tests rendering the arrow into a display buffer and comparing it
+//! against the bitmaps in `tests/golden`.
+//!
+//! Run with `UPDATE_GOLDEN=1` to rewrite the golden files after an intended change.
+
+use eink_arrow::arrow::{Arrow, ArrowStyle, Heading};
+use eink_arrow::frame::{Frame, Ink};
+use embedded_graphics::geometry::Size;
+use epd_waveshare::epd2in7b::{HEIGHT, WIDTH};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const RADII: [i32; 3] = [10, 20, 40];
+
+fn render_styled(heading: Heading, radius: i32, style: ArrowStyle) -> Frame {
+    let mut arrow = Arrow::new(radius);
+    arrow.x = WIDTH as i32 / 2;
+    arrow.y = HEIGHT as i32 / 2;
+    arrow.heading = heading;
+    arrow.style = style;
+
+    let mut frame = Frame::new(Size::new(WIDTH, HEIGHT));
+    arrow.draw(&mut frame);
+    frame
+}
+
+fn render(heading: Heading, radius: i32) -> Vec<u8> {
+    let frame = render_styled(heading, radius, ArrowStyle::default());
+    assert!(frame.red().iter().all(|&byte| byte == 0xff), "drew in red");
+    frame.black().to_vec()
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.pbm", name))
+}
+
+// Golden files are binary PBMs, which store set bits as black rather than white
+fn to_pbm(buffer: &[u8]) -> Vec<u8> {
+    let mut pbm = format!("P4\n{} {}\n", WIDTH, HEIGHT).into_bytes();
+    pbm.extend(buffer.iter().map(|byte| !byte));
+    pbm
+}
+
+fn from_pbm(pbm: &[u8]) -> Vec<u8> {
+    let header = format!("P4\n{} {}\n", WIDTH, HEIGHT);
+    assert!(
+        pbm.starts_with(header.as_bytes()),
+        "golden file is not a {}x{} binary PBM",
+        WIDTH,
+        HEIGHT
+    );
+    pbm[header.len()..].iter().map(|byte| !byte).collect()
+}
+
+fn is_black(buffer: &[u8], x: u32, y: u32) -> bool {
+    let index = (x / 8 + y * (WIDTH / 8)) as usize;
+    buffer[index] & (0x80 >> (x % 8)) == 0
+}
+
+/// Renders the area covered by either image, marking pixels that only
+/// appear in the expected image with `-` and only in the actual one with `+`
+fn ascii_diff(expected: &[u8], actual: &[u8]) -> String {
+    let covered: Vec<(u32, u32)> = (0..HEIGHT)
+        .flat_map(|y| (0..WIDTH).map(move |x| (x, y)))
+        .filter(|&(x, y)| is_black(expected, x, y) || is_black(actual, x, y))
+        .collect();
+    let min_x = covered.iter().map(|&(x, _)| x).min().unwrap_or(0);
+    let max_x = covered.iter().map(|&(x, _)| x).max().unwrap_or(0);
+    let min_y = covered.iter().map(|&(_, y)| y).min().unwrap_or(0);
+    let max_y = covered.iter().map(|&(_, y)| y).max().unwrap_or(0);
+
+    let mut diff = format!(
+        "x {}..={}, y {}..={} ('-' missing, '+' unexpected)\n",
+        min_x, max_x, min_y, max_y
+    );
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            diff.push(match (is_black(expected, x, y), is_black(actual, x, y)) {
+                (true, true) => '#',
+                (false, false) => '.',
+                (true, false) => '-',
+                (false, true) => '+',
+            });
+        }
+        diff.push('\n');
+    }
+    diff
+}
+
+fn assert_golden(name: &str, actual: &[u8]) {
+    let path = golden_path(name);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, to_pbm(actual)).expect("writing golden file");
+        return;
+    }
+
+    let pbm = fs::read(&path).unwrap_or_else(|e| {
+        panic!(
+            "reading {}: {} (run with UPDATE_GOLDEN=1)",
+            path.display(),
+            e
+        )
+    });
+    let expected = from_pbm(&pbm);
+    if expected != actual {
+        panic!(
+            "{} does not match {}\n{}",
+            name,
+            path.display(),
+            ascii_diff(&expected, actual)
+        );
+    }
+}
+
+fn assert_heading(heading: Heading, label: &str) {
+    for &radius in RADII.iter() {
+        let name = format!("{}_r{}", label, radius);
+        assert_golden(&name, &render(heading, radius));
+    }
+}
+
+#[test]
+fn draws_rotate0() {
+    assert_heading(Heading::DOWN, "rotate0");
+}
+
+#[test]
+fn draws_rotate90() {
+    assert_heading(Heading::LEFT, "rotate90");
+}
+
+#[test]
+fn draws_rotate180() {
+    assert_heading(Heading::UP, "rotate180");
+}
+
+#[test]
+fn draws_rotate270() {
+    assert_heading(Heading::RIGHT, "rotate270");
+}
+
+#[test]
+fn draws_diagonals() {
+    for &degrees in [45, 135, 225, 315].iter() {
+        assert_heading(
+            Heading::from_degrees(degrees),
+            &format!("heading{}", degrees),
+        );
+    }
+}
+
+#[test]
+fn draws_sixteenth_turn() {
+    assert_heading(Heading::from_tenths(225), "heading22_5");
+}
+
+#[test]
+fn splits_parts_between_layers() {
+    let style = ArrowStyle {
+        head: Ink::Red,
+        shaft: Ink::Black,
+    };
+    for &degrees in [0, 45, 90].iter() {
+        let heading = Heading::from_degrees(degrees);
+        let frame = render_styled(heading, 20, style);
+        assert!(frame.black().iter().any(|&byte| byte != 0xff), "no shaft");
+        assert!(frame.red().iter().any(|&byte| byte != 0xff), "no head");
+
+        // Together the layers cover exactly what an all black arrow does
+        let combined: Vec<u8> = frame
+            .black()
+            .iter()
+            .zip(frame.red())
+            .map(|(black, red)| black & red)
+            .collect();
+        assert_eq!(combined, render(heading, 20), "at {}", heading);
+    }
+}
@@ -0,0 +1,162 @@
+use eink_arrow::backend::{DisplayBackend, EpdBackend};
+use eink_arrow::error::Error;
+use eink_arrow::panel::Model;
+use embedded_hal::{blocking::spi, digital::v2};
+use std::cell::RefCell;
+use std::convert::Infallible;
+use std::io;
+use std::rc::Rc;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Event {
+    Busy,
+    Reset,
+    Write,
+}
+
+// The 1.54" panel's write RAM command, which the fake panel can hang on
+const WRITE_RAM: u8 = 0x24;
+
+/// A 1.54" panel, which holds BUSY high while it's busy
+#[derive(Default)]
+struct Panel {
+    log: Vec<Event>,
+    // Never comes out of busy, even when reset
+    stuck: bool,
+    // Hangs on writing RAM, until reset
+    hangs_on_write: bool,
+    busy: bool,
+    // DC is low while a command is sent
+    command: bool,
+}
+
+type Shared = Rc<RefCell<Panel>>;
+
+struct Spi(Shared);
+
+impl spi::Write<u8> for Spi {
+    type Error = io::Error;
+
+    fn write(&mut self, words: &[u8]) -> io::Result<()> {
+        let mut panel = self.0.borrow_mut();
+        panel.log.push(Event::Write);
+        if panel.hangs_on_write && panel.command && words == [WRITE_RAM] {
+            panel.busy = true;
+        }
+        Ok(())
+    }
+}
+
+struct Busy(Shared);
+
+impl v2::InputPin for Busy {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Infallible> {
+        let mut panel = self.0.borrow_mut();
+        let busy = panel.stuck || panel.busy;
+        if busy {
+            panel.log.push(Event::Busy);
+        }
+        Ok(busy)
+    }
+
+    fn is_low(&self) -> Result<bool, Infallible> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+enum Role {
+    Cs,
+    Dc,
+    Rst,
+}
+
+struct Output(Shared, Role);
+
+impl v2::OutputPin for Output {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        let mut panel = self.0.borrow_mut();
+        match self.1 {
+            Role::Cs => {}
+            Role::Dc => panel.command = true,
+            Role::Rst => {
+                panel.log.push(Event::Reset);
+                panel.busy = false;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        if let Role::Dc = self.1 {
+            self.0.borrow_mut().command = false;
+        }
+        Ok(())
+    }
+}
+
+fn backend(panel: &Shared) -> impl DisplayBackend<Error = Error> {
+    let mut backend = EpdBackend::new(
+        Model::Epd1in54,
+        Spi(panel.clone()),
+        Output(panel.clone(), Role::Cs),
+        Busy(panel.clone()),
+        Output(panel.clone(), Role::Dc),
+        Output(panel.clone(), Role::Rst),
+    );
+    backend.set_busy_timeout(Duration::from_millis(20), 2);
+    backend
+}
+
+fn busy_timeout<T: std::fmt::Debug>(result: Result<T, Error>) -> &'static str {
+    match result {
+        Err(Error::BusyTimeout { step }) => step,
+        other => panic!("expected a busy timeout, got {:?}", other),
+    }
+}
+
+fn resets(panel: &Shared) -> usize {
+    let panel = panel.borrow();
+    panel.log.iter().filter(|&&e| e == Event::Reset).count()
+}
+
+// Nothing more is sent after a wait times out, until the panel is reset
+fn assert_quiet_while_busy(panel: &Shared) {
+    let mut events = panel.borrow().log.clone();
+    events.dedup();
+    for pair in events.windows(2) {
+        assert_ne!(pair, [Event::Busy, Event::Write]);
+    }
+}
+
+#[test]
+fn retries_a_step_that_stays_busy_then_gives_up() {
+    let panel = Shared::default();
+    let mut backend = backend(&panel);
+    backend.init().unwrap();
+    panel.borrow_mut().hangs_on_write = true;
+
+    let white = vec![0xff; 200 * 200 / 8];
+    assert_eq!(
+        busy_timeout(backend.push_frame(&white, &white)),
+        "displaying frame"
+    );
+    // Reset once to start with, then once for each retry
+    assert_eq!(resets(&panel), 3);
+    assert_quiet_while_busy(&panel);
+}
+
+#[test]
+fn names_the_reset_when_that_stalls_too() {
+    let panel = Shared::default();
+    panel.borrow_mut().stuck = true;
+    let mut backend = backend(&panel);
+
+    assert_eq!(busy_timeout(backend.init()), "resetting");
+    assert_eq!(resets(&panel), 2);
+    assert_quiet_while_busy(&panel);
+}
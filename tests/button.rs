@@ -0,0 +1,91 @@
+use eink_arrow::button::{Press, PressDetector, PressTiming};
+use std::time::{Duration, Instant};
+
+fn detector(double_press_ms: u64) -> PressDetector {
+    PressDetector::new(PressTiming {
+        debounce: Duration::from_millis(50),
+        long_press: Duration::from_millis(800),
+        double_press: Duration::from_millis(double_press_ms),
+    })
+}
+
+fn ms(start: Instant, offset: u64) -> Instant {
+    start + Duration::from_millis(offset)
+}
+
+#[test]
+fn ignores_contact_bounce() {
+    let start = Instant::now();
+    let mut buttons = detector(0);
+    assert_eq!(buttons.edge(true, ms(start, 0)), None);
+    assert_eq!(buttons.edge(false, ms(start, 5)), None);
+    assert_eq!(buttons.edge(true, ms(start, 10)), None);
+    assert_eq!(buttons.edge(false, ms(start, 120)), Some(Press::Short));
+    assert_eq!(buttons.edge(true, ms(start, 130)), None);
+    assert_eq!(buttons.edge(false, ms(start, 140)), None);
+}
+
+#[test]
+fn reports_long_press_on_release() {
+    let start = Instant::now();
+    let mut buttons = detector(300);
+    buttons.edge(true, ms(start, 0));
+    assert_eq!(buttons.edge(false, ms(start, 900)), Some(Press::Long));
+    assert_eq!(buttons.deadline(), None);
+}
+
+#[test]
+fn waits_for_a_second_press_before_reporting_short() {
+    let start = Instant::now();
+    let mut buttons = detector(300);
+    buttons.edge(true, ms(start, 0));
+    assert_eq!(buttons.edge(false, ms(start, 100)), None);
+    assert_eq!(buttons.deadline(), Some(ms(start, 400)));
+    assert_eq!(buttons.poll(ms(start, 399)), None);
+    assert_eq!(buttons.poll(ms(start, 400)), Some(Press::Short));
+    assert_eq!(buttons.poll(ms(start, 500)), None);
+}
+
+#[test]
+fn reports_double_press() {
+    let start = Instant::now();
+    let mut buttons = detector(300);
+    buttons.edge(true, ms(start, 0));
+    buttons.edge(false, ms(start, 100));
+    buttons.edge(true, ms(start, 250));
+    // Still held down when the double press window ends
+    assert_eq!(buttons.poll(ms(start, 450)), None);
+    assert_eq!(buttons.edge(false, ms(start, 500)), Some(Press::Double));
+    assert_eq!(buttons.deadline(), None);
+}
+
+#[test]
+fn recovers_from_a_tap_shorter_than_the_debounce() {
+    let start = Instant::now();
+    let mut buttons = detector(0);
+    assert_eq!(buttons.edge(true, ms(start, 0)), None);
+    // Dropped as bounce, until nothing else happens for the debounce time
+    assert_eq!(buttons.edge(false, ms(start, 30)), None);
+    assert_eq!(buttons.deadline(), Some(ms(start, 80)));
+    assert_eq!(buttons.poll(ms(start, 79)), None);
+    assert_eq!(buttons.poll(ms(start, 80)), Some(Press::Short));
+
+    // Or until the next press, if that comes first
+    buttons.edge(true, ms(start, 1000));
+    buttons.edge(false, ms(start, 1030));
+    assert_eq!(buttons.edge(true, ms(start, 1500)), Some(Press::Short));
+    assert_eq!(buttons.edge(false, ms(start, 1600)), Some(Press::Short));
+}
+
+#[test]
+fn reports_a_short_press_before_a_long_one() {
+    let start = Instant::now();
+    let mut buttons = detector(300);
+    buttons.edge(true, ms(start, 0));
+    buttons.edge(false, ms(start, 100));
+    buttons.edge(true, ms(start, 250));
+    assert_eq!(buttons.edge(false, ms(start, 1100)), Some(Press::Short));
+    assert_eq!(buttons.deadline(), Some(ms(start, 1100)));
+    assert_eq!(buttons.poll(ms(start, 1100)), Some(Press::Long));
+    assert_eq!(buttons.deadline(), None);
+}
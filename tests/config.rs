@@ -0,0 +1,130 @@
+use eink_arrow::arrow::Heading;
+use eink_arrow::config::{Config, CsPin, Profile};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+fn config_file(name: &str, contents: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("eink-arrow-config-{}-{}.toml", name, process::id()));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn invalid(config: &Config) -> String {
+    config.validate().unwrap_err().to_string()
+}
+
+#[test]
+fn applies_the_file_on_top_of_the_profile() {
+    let path = config_file(
+        "merge",
+        "profile = \"hat\"\n[pins]\nbusy = 23\n[arrow]\nmove_distance = 40\n",
+    );
+    let config = Config::from_file(&path, None).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(config.profile, Profile::Hat);
+    // Only what the file sets changes, the rest comes from the profile
+    assert_eq!(
+        (
+            config.pins.cs,
+            config.pins.busy,
+            config.pins.dc,
+            config.pins.rst
+        ),
+        (CsPin::Hardware, 23, 25, 17)
+    );
+    assert_eq!(config.arrow.move_distance, 40);
+    assert_eq!(config.arrow.headings, 4);
+    assert_eq!(config.buttons.len(), 4);
+}
+
+#[test]
+fn picks_chip_select_for_either_profile() {
+    let cs = |profile: &str, file: &str| {
+        let path = config_file(profile, file);
+        let config = Config::from_file(&path, Some(profile.parse().unwrap()));
+        fs::remove_file(&path).unwrap();
+        config.map(|config| config.pins.cs)
+    };
+    assert_eq!(cs("breakout", "").unwrap(), CsPin::Gpio(5));
+    assert_eq!(cs("hat", "").unwrap(), CsPin::Hardware);
+    assert_eq!(
+        cs("breakout", "[pins]\ncs = \"hardware\"\n").unwrap(),
+        CsPin::Hardware
+    );
+    assert_eq!(cs("hat", "[pins]\ncs = 22\n").unwrap(), CsPin::Gpio(22));
+    assert!(cs("hat", "[pins]\ncs = \"software\"\n").is_err());
+
+    let hat = Config::default_toml(Profile::Hat);
+    assert!(hat.contains("cs = 'hardware'"), "{}", hat);
+}
+
+#[test]
+fn rejects_unknown_fields() {
+    let path = config_file("unknown", "[arrow]\nmove_distanse = 40\n");
+    let error = Config::from_file(&path, None).unwrap_err().to_string();
+    fs::remove_file(&path).unwrap();
+    assert!(error.contains("move_distanse"), "{}", error);
+}
+
+#[test]
+fn rejects_pins_off_the_header() {
+    let mut config = Config::default();
+    config.pins.busy = 28;
+    assert_eq!(
+        invalid(&config),
+        "invalid config: pins.busy is set to GPIO 28, but only 0 to 27 are available"
+    );
+}
+
+#[test]
+fn turns_sixteen_ways() {
+    let mut config = Config::default();
+    config.arrow.headings = 16;
+    assert!(config.validate().is_ok());
+    let mut arrow = config.arrow.arrow(&config.display);
+    arrow.rotate();
+    assert_eq!(arrow.heading, Heading::from_tenths(225));
+    for _ in 1..16 {
+        arrow.rotate();
+    }
+    assert_eq!(arrow.heading, Heading::DOWN);
+    arrow.rotate_left();
+    assert_eq!(arrow.heading.to_string(), "337.5°");
+
+    config.arrow.headings = 7;
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn rejects_moves_longer_than_the_panel() {
+    let mut config = Config::default();
+    config.arrow.move_distance = -264;
+    assert!(config.validate().is_ok());
+    config.arrow.move_distance = 265;
+    assert_eq!(
+        invalid(&config),
+        "invalid config: arrow.move_distance must be between -264 and 264 on the epd2in7b panel, got 265"
+    );
+}
+
+#[test]
+fn rejects_pins_used_twice() {
+    let mut config = Config::default();
+    config.buttons[1].pin = 6;
+    assert_eq!(
+        invalid(&config),
+        "invalid config: GPIO 6 is used for both pins.dc and button 'rotate'"
+    );
+
+    let mut config = Config::default();
+    config.buttons[0].pin = 10;
+    assert_eq!(
+        invalid(&config),
+        "invalid config: GPIO 10 is used for both SPI0 MOSI and button 'move'"
+    );
+    // SPI1 leaves SPI0's pins free
+    config.spi.device = PathBuf::from("/dev/spidev1.0");
+    assert!(config.validate().is_ok());
+}
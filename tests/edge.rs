@@ -0,0 +1,114 @@
+use eink_arrow::arrow::{Arrow, EdgeMode, Heading};
+use embedded_graphics::geometry::Size;
+
+// The default panel is 176 by 264, so a radius 10 arrow's center stays within
+// 10..=166 across and 10..=254 down
+fn arrow(edge_mode: EdgeMode, heading: Heading) -> Arrow {
+    let mut arrow = Arrow::new(10);
+    arrow.edge_mode = edge_mode;
+    arrow.heading = heading;
+    arrow
+}
+
+#[test]
+fn survives_huge_distances() {
+    for &distance in &[i32::MAX, i32::MIN] {
+        let mut clamped = arrow(EdgeMode::Clamp, Heading::DOWN);
+        clamped.move_forward(distance);
+        let edge = if distance > 0 { 254 } else { 10 };
+        assert_eq!((clamped.x, clamped.y), (10, edge));
+
+        for &edge_mode in &[EdgeMode::Wrap, EdgeMode::Bounce] {
+            let mut arrow = arrow(edge_mode, Heading::DOWN);
+            arrow.move_forward(distance);
+            assert!(
+                (10..=254).contains(&arrow.y),
+                "{:?} left the panel",
+                edge_mode
+            );
+        }
+    }
+}
+
+// Where the arrow ends up and which way it faces after moving `distance`
+// from `y`, straight down or up the panel
+fn vertical(edge_mode: EdgeMode, heading: Heading, y: i32, distance: i32) -> (i32, Heading) {
+    let mut arrow = arrow(edge_mode, heading);
+    arrow.y = y;
+    arrow.move_forward(distance);
+    assert_eq!(arrow.x, 10, "moved sideways");
+    (arrow.y, arrow.heading)
+}
+
+#[test]
+fn clamps_to_the_edge() {
+    let clamp = |heading, y, distance| vertical(EdgeMode::Clamp, heading, y, distance);
+    assert_eq!(clamp(Heading::DOWN, 250, 5), (254, Heading::DOWN));
+    assert_eq!(clamp(Heading::DOWN, 10, 493), (254, Heading::DOWN));
+    assert_eq!(clamp(Heading::UP, 15, 10), (10, Heading::UP));
+}
+
+#[test]
+fn wraps_to_the_opposite_edge() {
+    let wrap = |heading, y, distance| vertical(EdgeMode::Wrap, heading, y, distance);
+    assert_eq!(wrap(Heading::DOWN, 250, 5), (10, Heading::DOWN));
+    // More than a whole span past the edge goes round again
+    assert_eq!(wrap(Heading::DOWN, 10, 493), (13, Heading::DOWN));
+    assert_eq!(wrap(Heading::UP, 15, 10), (250, Heading::UP));
+}
+
+#[test]
+fn bounces_off_the_edge() {
+    let bounce = |heading, y, distance| vertical(EdgeMode::Bounce, heading, y, distance);
+    assert_eq!(bounce(Heading::DOWN, 250, 5), (253, Heading::UP));
+    // Off the bottom and then the top, so facing the same way again
+    assert_eq!(bounce(Heading::DOWN, 10, 493), (15, Heading::DOWN));
+    assert_eq!(bounce(Heading::UP, 15, 10), (15, Heading::DOWN));
+}
+
+#[test]
+fn stays_put_without_room_to_move() {
+    for &edge_mode in &[EdgeMode::Clamp, EdgeMode::Wrap, EdgeMode::Bounce] {
+        let mut arrow = arrow(edge_mode, Heading::RIGHT);
+        arrow.bounds = Size::new(20, 264);
+        arrow.move_forward(30);
+        assert_eq!(
+            (arrow.x, arrow.heading),
+            (10, Heading::RIGHT),
+            "{:?}",
+            edge_mode
+        );
+    }
+}
+
+#[test]
+fn reflects_diagonals_on_the_axes_that_hit() {
+    let down_left = Heading::from_degrees(45);
+    let mut one_axis = arrow(EdgeMode::Bounce, down_left);
+    one_axis.x = 100;
+    one_axis.y = 250;
+    one_axis.move_forward(10);
+    assert_eq!((one_axis.x, one_axis.y), (93, 251));
+    assert_eq!(one_axis.heading, Heading::from_degrees(135));
+
+    let mut both_axes = arrow(EdgeMode::Bounce, down_left);
+    both_axes.x = 12;
+    both_axes.y = 250;
+    both_axes.move_forward(10);
+    assert_eq!((both_axes.x, both_axes.y), (15, 251));
+    assert_eq!(both_axes.heading, Heading::from_degrees(225));
+}
+
+#[test]
+fn moves_along_diagonals() {
+    let mut arrow = arrow(EdgeMode::Clamp, Heading::from_degrees(30));
+    arrow.x = 100;
+    arrow.y = 100;
+    arrow.move_forward(100);
+    // 100 * sin 30° to the left and 100 * cos 30° down
+    assert_eq!((arrow.x, arrow.y), (50, 187));
+
+    arrow.heading = Heading::from_degrees(225);
+    arrow.move_forward(20);
+    assert_eq!((arrow.x, arrow.y), (64, 173));
+}
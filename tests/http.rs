@@ -0,0 +1,141 @@
+use eink_arrow::app::App;
+use eink_arrow::arrow::{Arrow, ArrowMessage, Heading};
+use eink_arrow::backend::{DisplayBackend, MemoryBackend};
+use eink_arrow::http::HttpApi;
+use eink_arrow::panel::Orientation;
+use eink_arrow::scene::Scene;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::mpsc::{self, Receiver};
+
+fn serve() -> (SocketAddr, Receiver<ArrowMessage>) {
+    serve_scene(Scene::from(Arrow::new(10)))
+}
+
+fn serve_scene(scene: Scene) -> (SocketAddr, Receiver<ArrowMessage>) {
+    let mut app = App::new(MemoryBackend::new(), scene);
+    app.start().unwrap();
+    let (tx, rx) = mpsc::channel();
+    let api = HttpApi::new(
+        tx,
+        app.scene(),
+        app.shown(),
+        app.backend().size(),
+        Orientation::Portrait,
+        25,
+    );
+    (api.spawn("127.0.0.1:0").unwrap(), rx)
+}
+
+fn request(address: SocketAddr, method: &str, path: &str) -> (u16, Vec<u8>) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        method, path
+    )
+    .unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    let split = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .expect("response has headers");
+    let status_line = String::from_utf8_lossy(&response[..split]).into_owned();
+    let status = status_line.split(' ').nth(1).unwrap().parse().unwrap();
+    (status, response[split + 4..].to_vec())
+}
+
+#[test]
+fn queues_moves_and_turns() {
+    let (address, rx) = serve();
+    assert_eq!(request(address, "POST", "/move?distance=30").0, 202);
+    assert_eq!(request(address, "POST", "/move").0, 202);
+    assert_eq!(request(address, "POST", "/rotate").0, 202);
+    assert_eq!(request(address, "POST", "/rotate?direction=left").0, 202);
+
+    let received: Vec<ArrowMessage> = rx.try_iter().collect();
+    assert_eq!(
+        received,
+        vec![
+            ArrowMessage::MoveForward(30),
+            ArrowMessage::MoveForward(25),
+            ArrowMessage::Rotate,
+            ArrowMessage::RotateLeft,
+        ]
+    );
+}
+
+#[test]
+fn queues_absolute_positions() {
+    let (address, rx) = serve();
+    assert_eq!(request(address, "POST", "/goto?x=80&y=120").0, 202);
+    assert_eq!(request(address, "POST", "/face?heading=east").0, 202);
+    assert_eq!(request(address, "POST", "/radius?radius=20").0, 202);
+    assert_eq!(request(address, "POST", "/reset").0, 202);
+    assert_eq!(request(address, "POST", "/goto?x=0&y=120").0, 400);
+    assert_eq!(request(address, "POST", "/radius?radius=500").0, 400);
+
+    let received: Vec<ArrowMessage> = rx.try_iter().collect();
+    assert_eq!(
+        received,
+        vec![
+            ArrowMessage::MoveTo { x: 80, y: 120 },
+            ArrowMessage::Face(Heading::RIGHT),
+            ArrowMessage::SetRadius(20),
+            ArrowMessage::Reset,
+        ]
+    );
+}
+
+#[test]
+fn reports_the_arrow_and_frame() {
+    let (address, _rx) = serve();
+    let (status, body) = request(address, "GET", "/state");
+    assert_eq!(status, 200);
+    assert_eq!(
+        body,
+        br#"{"selected":1,"arrows":[{"id":1,"x":10,"y":10,"heading":0.0}]}"#
+    );
+
+    let (status, body) = request(address, "GET", "/frame.png");
+    assert_eq!(status, 200);
+    assert!(body.starts_with(b"\x89PNG"));
+}
+
+#[test]
+fn sends_requests_to_the_arrow_asked_for() {
+    let mut second = Arrow::new(10);
+    second.id = 2;
+    second.x = 100;
+    let (address, rx) = serve_scene(Scene::new(vec![Arrow::new(10), second]));
+    assert_eq!(request(address, "POST", "/move?arrow=2&distance=30").0, 202);
+    assert_eq!(request(address, "POST", "/goto?x=50&y=50&arrow=1").0, 202);
+    assert_eq!(request(address, "POST", "/rotate?arrow=3").0, 400);
+    assert_eq!(request(address, "POST", "/reset?arrow=two").0, 400);
+    assert_eq!(
+        rx.try_iter().collect::<Vec<_>>(),
+        vec![
+            ArrowMessage::MoveForward(30).to(2),
+            ArrowMessage::MoveTo { x: 50, y: 50 }.to(1),
+        ]
+    );
+
+    let (status, body) = request(address, "GET", "/state");
+    assert_eq!(status, 200);
+    assert_eq!(
+        body,
+        &br#"{"selected":1,"arrows":[{"id":1,"x":10,"y":10,"heading":0.0},{"id":2,"x":100,"y":10,"heading":0.0}]}"#[..]
+    );
+}
+
+#[test]
+fn rejects_bad_requests() {
+    let (address, rx) = serve();
+    assert_eq!(request(address, "POST", "/move?distance=far").0, 400);
+    assert_eq!(request(address, "POST", "/move?distance=2147483647").0, 400);
+    assert_eq!(request(address, "POST", "/rotate?direction=up").0, 400);
+    assert_eq!(request(address, "GET", "/move").0, 405);
+    assert_eq!(request(address, "GET", "/nowhere").0, 404);
+    assert_eq!(rx.try_iter().count(), 0);
+}
@@ -0,0 +1,97 @@
+//! Run the broker test with `cargo test --features mqtt -- --ignored` while a
+//! broker such as mosquitto is listening on localhost:1883.
+#![cfg(feature = "mqtt")]
+
+use eink_arrow::arrow::{Arrow, ArrowMessage, Heading};
+use eink_arrow::config::MqttConfig;
+use eink_arrow::mqtt::{self, Mqtt};
+use eink_arrow::scene::Scene;
+use rumqttc::{Client, Event, MqttOptions, Packet, QoS};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn maps_commands_to_messages() {
+    let message = |command, payload: &str| mqtt::message(command, payload.as_bytes(), 25);
+    assert_eq!(message("rotate", ""), Ok(ArrowMessage::Rotate));
+    assert_eq!(message("rotate", "right"), Ok(ArrowMessage::Rotate));
+    assert_eq!(message("rotate", "left"), Ok(ArrowMessage::RotateLeft));
+    assert_eq!(message("move", ""), Ok(ArrowMessage::MoveForward(25)));
+    assert_eq!(
+        message("move", " -40\n"),
+        Ok(ArrowMessage::MoveForward(-40))
+    );
+    assert_eq!(
+        message("goto", "80 120"),
+        Ok(ArrowMessage::MoveTo { x: 80, y: 120 })
+    );
+    assert_eq!(message("face", "up"), Ok(ArrowMessage::Face(Heading::UP)));
+    assert_eq!(message("radius", "20"), Ok(ArrowMessage::SetRadius(20)));
+    assert_eq!(message("reset", ""), Ok(ArrowMessage::Reset));
+    assert_eq!(
+        message("arrow/2/move", "40"),
+        Ok(ArrowMessage::MoveForward(40).to(2))
+    );
+}
+
+#[test]
+fn rejects_unknown_commands() {
+    let message = |command, payload: &str| mqtt::message(command, payload.as_bytes(), 25);
+    assert!(message("rotate", "up").is_err());
+    assert!(message("move", "far").is_err());
+    assert!(message("goto", "80").is_err());
+    assert!(message("state", "").is_err());
+    assert!(message("arrow/2", "").is_err());
+    assert!(message("arrow/two/move", "").is_err());
+    assert!(mqtt::message("move", &[0xff], 25).is_err());
+}
+
+#[test]
+#[ignore]
+fn talks_to_a_local_broker() {
+    let config = MqttConfig {
+        enabled: true,
+        client_id: "eink-arrow-test".into(),
+        topic: "eink-arrow-test".into(),
+        ..MqttConfig::default()
+    };
+    let (tx, rx) = mpsc::channel();
+    let arrow_client = Mqtt::start(&config, tx, 25);
+
+    let options = MqttOptions::new("eink-arrow-test-remote", "localhost", 1883);
+    let (remote, mut connection) = Client::new(options, 10);
+    remote
+        .subscribe("eink-arrow-test/state", QoS::AtLeastOnce)
+        .unwrap();
+    let (state_tx, state_rx) = mpsc::channel();
+    thread::spawn(move || {
+        for event in connection.iter() {
+            if let Ok(Event::Incoming(Packet::Publish(publish))) = event {
+                let _ = state_tx.send(publish.payload.to_vec());
+            }
+        }
+    });
+
+    // Keep asking until the arrow's client has subscribed
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let received = loop {
+        remote
+            .publish("eink-arrow-test/move", QoS::AtLeastOnce, false, "40")
+            .unwrap();
+        if let Ok(message) = rx.recv_timeout(Duration::from_millis(500)) {
+            break message;
+        }
+        assert!(Instant::now() < deadline, "no command arrived");
+    };
+    assert_eq!(received, ArrowMessage::MoveForward(40));
+
+    let mut scene = Scene::from(Arrow::new(10));
+    scene.apply(received);
+    arrow_client.publish(&scene);
+    let state = state_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert_eq!(
+        state,
+        br#"{"selected":1,"arrows":[{"id":1,"x":10,"y":50,"heading":0.0}]}"#
+    );
+}
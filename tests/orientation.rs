@@ -0,0 +1,51 @@
+use eink_arrow::arrow::ArrowMessage;
+use eink_arrow::config::{ArrowConfig, Config, DisplayConfig};
+use eink_arrow::frame::{Frame, Ink};
+use eink_arrow::panel::{Model, Orientation};
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+
+fn black_pixels(frame: &Frame) -> Vec<(u32, u32)> {
+    let width = frame.size().width;
+    let row_bytes = width.div_ceil(8);
+    (0..frame.size().height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|&(x, y)| frame.black()[(x / 8 + y * row_bytes) as usize] & (0x80 >> (x % 8)) == 0)
+        .collect()
+}
+
+#[test]
+fn draws_where_the_mounted_panel_shows_it() {
+    let native = Model::Epd2in7b.size();
+    let corners = [
+        (Orientation::Portrait, (0, 0)),
+        (Orientation::Landscape, (native.width - 1, 0)),
+        (
+            Orientation::PortraitFlipped,
+            (native.width - 1, native.height - 1),
+        ),
+        (Orientation::LandscapeFlipped, (0, native.height - 1)),
+    ];
+    for &(orientation, expected) in &corners {
+        let mut frame = Frame::rotated(native, orientation);
+        Pixel(Point::zero(), BinaryColor::On)
+            .draw(frame.layer_mut(Ink::Black))
+            .unwrap();
+        assert_eq!(black_pixels(&frame), vec![expected], "{:?}", orientation);
+    }
+}
+
+#[test]
+fn keeps_the_arrow_within_the_panel_as_mounted() {
+    let display = DisplayConfig {
+        orientation: Orientation::Landscape,
+        ..Config::default().display
+    };
+    let mut arrow = ArrowConfig::default().arrow(&display);
+    assert_eq!(arrow.bounds, Size::new(264, 176));
+
+    arrow.apply(ArrowMessage::MoveForward(1000));
+    assert_eq!(arrow.y, 176 - arrow.radius);
+    arrow.apply(ArrowMessage::RotateLeft);
+    arrow.apply(ArrowMessage::MoveForward(1000));
+    assert_eq!(arrow.x, 264 - arrow.radius);
+}
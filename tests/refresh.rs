@@ -0,0 +1,147 @@
+use eink_arrow::app::App;
+use eink_arrow::arrow::{Arrow, ArrowMessage};
+use eink_arrow::backend::{DisplayBackend, MemoryBackend};
+use eink_arrow::frame::{self, Frame, Ink};
+use eink_arrow::panel::Model;
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*, primitives::Rectangle};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+fn app(partial: bool, full_refresh_every: u32) -> App<MemoryBackend> {
+    let mut backend = MemoryBackend::new();
+    backend.set_partial(partial);
+    let mut app = App::new(backend, Arrow::new(10));
+    app.set_full_refresh_every(full_refresh_every);
+    app.start().unwrap();
+    app
+}
+
+fn refreshes(app: &App<MemoryBackend>) -> (usize, usize) {
+    (app.backend().refreshes(), app.backend().partial_refreshes())
+}
+
+#[test]
+fn refreshes_only_the_changed_window() {
+    let mut app = app(true, 10);
+    app.handle(ArrowMessage::MoveForward(30)).unwrap();
+    assert_eq!(refreshes(&app), (1, 1));
+    assert_eq!(app.backend().frame(), app.frame().black());
+    assert_eq!(app.backend().red(), app.frame().red());
+}
+
+#[test]
+fn keeps_the_window_on_a_narrow_panel() {
+    // 122 pixels wide, so the last byte of each row is only partly on the panel
+    let size = Model::Epd2in13V2.size();
+    let shown = Frame::new(size);
+    let mut frame = Frame::new(size);
+    Pixel(Point::new(121, 40), BinaryColor::On)
+        .draw(frame.layer_mut(Ink::Black))
+        .unwrap();
+
+    let window = frame.dirty_window(shown.black(), shown.red()).unwrap();
+    assert_eq!(window, Rectangle::new(Point::new(120, 40), Size::new(2, 1)));
+    assert_eq!(frame::crop(frame.black(), size.width, &window), vec![0xbf]);
+
+    let mut backend = MemoryBackend::for_model(Model::Epd2in13V2);
+    backend
+        .push_window(frame.black(), frame.red(), window)
+        .unwrap();
+    assert_eq!(backend.frame(), frame.black());
+}
+
+#[test]
+fn forces_a_full_refresh_periodically() {
+    let mut app = app(true, 2);
+    for _ in 0..3 {
+        app.handle(ArrowMessage::MoveForward(30)).unwrap();
+    }
+    assert_eq!(refreshes(&app), (2, 2));
+    app.handle(ArrowMessage::MoveForward(30)).unwrap();
+    assert_eq!(refreshes(&app), (2, 3));
+}
+
+#[test]
+fn falls_back_to_full_refreshes() {
+    let mut app = app(false, 10);
+    app.handle(ArrowMessage::MoveForward(30)).unwrap();
+    assert_eq!(refreshes(&app), (2, 0));
+}
+
+#[test]
+fn skips_refreshes_that_change_nothing() {
+    let mut app = app(true, 10);
+    app.handle(ArrowMessage::Rotate).unwrap();
+    app.handle(ArrowMessage::MoveForward(30)).unwrap();
+    assert_eq!(refreshes(&app), (1, 1));
+}
+
+#[test]
+fn refreshes_once_for_messages_that_queued_up() {
+    let mut app = app(false, 10);
+    let (tx, rx) = mpsc::channel();
+    for _ in 0..3 {
+        tx.send(ArrowMessage::MoveForward(20)).unwrap();
+    }
+    tx.send(ArrowMessage::Shutdown).unwrap();
+    app.run(rx).unwrap();
+    assert_eq!(refreshes(&app), (2, 0));
+    let scene = app.scene();
+    let scene = scene.lock().unwrap();
+    assert_eq!((scene.selected().x, scene.selected().y), (10, 70));
+}
+
+#[test]
+fn waits_out_the_settle_window() {
+    let mut app = app(false, 10);
+    app.set_settle(Duration::from_millis(500));
+    let (tx, rx) = mpsc::channel();
+    let sender = thread::spawn(move || {
+        for _ in 0..3 {
+            tx.send(ArrowMessage::MoveForward(20)).unwrap();
+            thread::sleep(Duration::from_millis(10));
+        }
+    });
+    // Returns once the sender is dropped
+    app.run(rx).unwrap();
+    sender.join().unwrap();
+    assert_eq!(refreshes(&app), (2, 0));
+}
+
+#[test]
+fn tells_listeners_about_each_refresh() {
+    let (tx, rx) = mpsc::channel();
+    let mut app = App::new(MemoryBackend::new(), Arrow::new(10));
+    app.on_refresh(move |scene| {
+        let arrow = scene.selected();
+        tx.send((arrow.x, arrow.y)).unwrap()
+    });
+    app.start().unwrap();
+    app.handle(ArrowMessage::MoveForward(30)).unwrap();
+    app.handle(ArrowMessage::Rotate).unwrap();
+    // Blocked by the edge, so nothing to refresh
+    app.handle(ArrowMessage::MoveForward(30)).unwrap();
+    assert_eq!(
+        rx.try_iter().collect::<Vec<_>>(),
+        vec![(10, 10), (10, 40), (10, 40)]
+    );
+}
+
+#[test]
+fn ignores_messages_that_leave_the_panel() {
+    let mut app = app(false, 10);
+    app.handle(ArrowMessage::MoveTo { x: 0, y: 50 }).unwrap();
+    app.handle(ArrowMessage::SetRadius(0)).unwrap();
+    app.handle(ArrowMessage::MoveForward(i32::MIN)).unwrap();
+    assert_eq!(refreshes(&app), (1, 0));
+
+    app.handle(ArrowMessage::MoveTo { x: 165, y: 50 }).unwrap();
+    app.handle(ArrowMessage::SetRadius(20)).unwrap();
+    assert_eq!(refreshes(&app), (3, 0));
+    let scene = app.scene();
+    let scene = scene.lock().unwrap();
+    let arrow = scene.selected();
+    // Moved back in just far enough for the bigger arrow to fit
+    assert_eq!((arrow.x, arrow.y, arrow.radius), (156, 50, 20));
+}
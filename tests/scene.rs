@@ -0,0 +1,116 @@
+use eink_arrow::app::App;
+use eink_arrow::arrow::{ArrowMessage, Heading};
+use eink_arrow::backend::MemoryBackend;
+use eink_arrow::button::Press;
+use eink_arrow::config::{Config, PlayerConfig};
+use eink_arrow::frame::{Frame, Ink};
+use eink_arrow::scene::Scene;
+use eink_arrow::state::SceneState;
+use std::env;
+use std::fs;
+use std::process;
+
+fn player(id: u8, ink: Ink, x: i32, y: i32, heading: u16) -> PlayerConfig {
+    PlayerConfig {
+        id,
+        ink: Some(ink),
+        x: Some(x),
+        y: Some(y),
+        heading,
+    }
+}
+
+fn two_players() -> Config {
+    Config {
+        players: vec![
+            player(1, Ink::Black, 30, 30, 0),
+            player(2, Ink::Red, 140, 230, 180),
+        ],
+        ..Config::default()
+    }
+}
+
+fn positions(scene: &Scene) -> Vec<(i32, i32, Heading)> {
+    scene
+        .arrows()
+        .iter()
+        .map(|arrow| (arrow.x, arrow.y, arrow.heading))
+        .collect()
+}
+
+#[test]
+fn sends_messages_to_their_arrow() {
+    let mut app = App::new(MemoryBackend::new(), two_players().scene());
+    app.start().unwrap();
+    app.handle(ArrowMessage::MoveForward(20).to(2)).unwrap();
+    app.handle(ArrowMessage::MoveForward(10)).unwrap();
+    app.handle(ArrowMessage::SelectNext).unwrap();
+    app.handle(ArrowMessage::Rotate).unwrap();
+    // There's no arrow 3, so nothing moves
+    app.handle(ArrowMessage::Reset.to(3)).unwrap();
+
+    let scene = app.scene();
+    let scene = scene.lock().unwrap();
+    assert_eq!(
+        positions(&scene),
+        vec![(30, 40, Heading::DOWN), (140, 210, Heading::RIGHT)]
+    );
+    assert_eq!(scene.selected().id, 2);
+    assert_eq!(app.backend().refreshes(), 4);
+}
+
+#[test]
+fn draws_each_arrow_in_its_own_ink() {
+    let config = two_players();
+    let mut scene_frame = Frame::new(config.display.size());
+    config.scene().draw(&mut scene_frame);
+
+    let mut alone = Frame::new(config.display.size());
+    let first = config.players[0].arrow(&config.arrow, &config.display);
+    first.draw(&mut alone);
+    assert_eq!(scene_frame.black(), alone.black());
+    let second = config.players[1].arrow(&config.arrow, &config.display);
+    second.draw(&mut alone);
+    assert_eq!(scene_frame.red(), alone.red());
+}
+
+#[test]
+fn validates_players_and_their_buttons() {
+    let mut config = two_players();
+    config.buttons[0].arrow = Some(2);
+    assert!(config.validate().is_ok());
+    assert_eq!(
+        config.buttons[0].message(Press::Short, 25),
+        Some(ArrowMessage::MoveForward(25).to(2))
+    );
+
+    config.buttons[0].arrow = Some(3);
+    assert!(config.validate().is_err());
+
+    let mut config = two_players();
+    config.players[1].id = 1;
+    assert!(config.validate().is_err());
+
+    let mut config = two_players();
+    config.players[1].x = Some(500);
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn saves_and_restores_every_arrow() {
+    let path = env::temp_dir().join(format!("eink-arrow-scene-{}.toml", process::id()));
+    let config = two_players();
+    let mut scene = config.scene();
+    scene.apply(ArrowMessage::MoveForward(20).to(1));
+    scene.apply(ArrowMessage::Rotate.to(2));
+    SceneState::of(&scene).save(&path).unwrap();
+
+    let mut restored = config.scene();
+    SceneState::load(&path)
+        .unwrap()
+        .expect("state was saved")
+        .restore(&mut restored)
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(positions(&restored), positions(&scene));
+}
@@ -0,0 +1,105 @@
+use eink_arrow::app::App;
+use eink_arrow::arrow::{Arrow, ArrowMessage, Heading};
+use eink_arrow::backend::MemoryBackend;
+use eink_arrow::script::{self, Command};
+use std::fs;
+use std::sync::mpsc;
+use std::time::Duration;
+
+fn parse(line: &str) -> Result<Option<Command>, String> {
+    Command::parse(line, 25)
+}
+
+#[test]
+fn parses_each_command() {
+    let send = |message| Ok(Some(Command::Send(message)));
+    assert_eq!(parse("rotate"), send(ArrowMessage::Rotate));
+    assert_eq!(parse("rotate left"), send(ArrowMessage::RotateLeft));
+    assert_eq!(parse("move"), send(ArrowMessage::MoveForward(25)));
+    assert_eq!(parse("  move 30  "), send(ArrowMessage::MoveForward(30)));
+    assert_eq!(
+        parse("goto 80 120"),
+        send(ArrowMessage::MoveTo { x: 80, y: 120 })
+    );
+    assert_eq!(parse("face east"), send(ArrowMessage::Face(Heading::RIGHT)));
+    assert_eq!(parse("face 90"), send(ArrowMessage::Face(Heading::LEFT)));
+    assert_eq!(parse("radius 20"), send(ArrowMessage::SetRadius(20)));
+    assert_eq!(parse("refresh # full"), send(ArrowMessage::Refresh));
+    assert_eq!(
+        parse("sleep 5s"),
+        Ok(Some(Command::Sleep(Duration::from_secs(5))))
+    );
+    assert_eq!(
+        parse("sleep 250ms"),
+        Ok(Some(Command::Sleep(Duration::from_millis(250))))
+    );
+    assert_eq!(
+        parse("sleep 2"),
+        Ok(Some(Command::Sleep(Duration::from_secs(2))))
+    );
+    assert_eq!(
+        parse("arrow 2 move 30"),
+        send(ArrowMessage::MoveForward(30).to(2))
+    );
+    assert_eq!(parse(""), Ok(None));
+    assert_eq!(parse("# just a comment"), Ok(None));
+}
+
+#[test]
+fn rejects_bad_lines() {
+    for line in &[
+        "jump",
+        "move far",
+        "goto 80",
+        "face sideways",
+        "sleep 5h",
+        "sleep 99999999999999999999",
+        "arrow",
+        "arrow 2",
+        "arrow two move",
+        "arrow 2 sleep 5",
+        "arrow 2 refresh",
+        "rotate up",
+    ] {
+        assert!(parse(line).is_err(), "{} should not parse", line);
+    }
+}
+
+#[test]
+fn reports_the_line_of_a_mistake() {
+    let path = std::env::temp_dir().join(format!("eink-arrow-script-{}.txt", std::process::id()));
+    fs::write(&path, "move\n\nrotate\nmove twice\n").unwrap();
+    let error = script::load(&path, 25).unwrap_err().to_string();
+    fs::remove_file(&path).unwrap();
+    assert!(
+        error.ends_with(":4: 'twice' is not a whole number of pixels"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn plays_a_script_through_the_app() {
+    let mut app = App::new(MemoryBackend::new(), Arrow::new(10));
+    app.start().unwrap();
+    let commands = [
+        "goto 80 120",
+        "sleep 1ms",
+        "face right",
+        "move 30",
+        "refresh",
+    ]
+    .iter()
+    .filter_map(|line| parse(line).unwrap())
+    .collect();
+    let (tx, rx) = mpsc::channel();
+    script::play(commands, tx);
+    app.run(rx).unwrap();
+
+    let scene = app.scene();
+    let scene = scene.lock().unwrap();
+    let arrow = scene.selected();
+    assert_eq!((arrow.x, arrow.y), (110, 120));
+    assert_eq!(arrow.heading, Heading::RIGHT);
+    assert!(app.backend().refreshes() >= 2);
+}
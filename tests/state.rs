@@ -0,0 +1,124 @@
+use eink_arrow::app::App;
+use eink_arrow::arrow::{Arrow, ArrowMessage, EdgeMode, Heading};
+use eink_arrow::backend::MemoryBackend;
+use eink_arrow::frame::Ink;
+use eink_arrow::state::ArrowState;
+use eink_arrow::trail::{Trail, TrailStyle};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::sync::mpsc;
+
+fn state_path(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("eink-arrow-{}-{}.toml", name, process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn arrow_with_trail() -> Arrow {
+    let mut arrow = Arrow::new(10);
+    arrow.trail = Trail::new(4, TrailStyle::Dots, Ink::Black);
+    arrow
+}
+
+#[test]
+fn round_trips_position_heading_and_trail() {
+    let path = state_path("round-trip");
+    let mut arrow = arrow_with_trail();
+    arrow.apply(ArrowMessage::MoveForward(30));
+    arrow.apply(ArrowMessage::RotateLeft);
+    arrow.apply(ArrowMessage::MoveForward(40));
+    ArrowState::of(&arrow).save(&path).unwrap();
+
+    let mut restored = arrow_with_trail();
+    ArrowState::load(&path)
+        .unwrap()
+        .expect("state was saved")
+        .restore(&mut restored)
+        .unwrap();
+    assert_eq!((restored.x, restored.y), (50, 40));
+    assert_eq!(restored.heading, Heading::RIGHT);
+    assert_eq!(
+        restored.trail.steps().collect::<Vec<_>>(),
+        arrow.trail.steps().collect::<Vec<_>>()
+    );
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn loads_nothing_before_the_first_save() {
+    let path = state_path("missing");
+    assert_eq!(ArrowState::load(&path).unwrap(), None);
+}
+
+#[test]
+fn app_saves_after_applying_messages() {
+    let path = state_path("app");
+    let mut app = App::new(MemoryBackend::new(), Arrow::new(10));
+    app.set_state_file(path.clone());
+    app.start().unwrap();
+    assert_eq!(ArrowState::load(&path).unwrap().unwrap().y, 10);
+
+    let (tx, rx) = mpsc::channel();
+    tx.send(ArrowMessage::MoveForward(25)).unwrap();
+    tx.send(ArrowMessage::Shutdown).unwrap();
+    app.run(rx).unwrap();
+
+    let state = ArrowState::load(&path).unwrap().unwrap();
+    assert_eq!((state.x, state.y, state.heading), (10, 35, 0.0));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn start_leaves_an_up_to_date_panel_alone() {
+    let path = state_path("frame");
+    let mut first = App::new(MemoryBackend::new(), Arrow::new(10));
+    first.set_frame_file(path.clone());
+    first.start().unwrap();
+    assert_eq!(first.backend().refreshes(), 1);
+
+    let mut same = App::new(MemoryBackend::new(), Arrow::new(10));
+    same.set_frame_file(path.clone());
+    same.start().unwrap();
+    assert_eq!(same.backend().refreshes(), 0);
+
+    let mut moved = Arrow::new(10);
+    moved.move_forward(20);
+    let mut changed = App::new(MemoryBackend::new(), moved);
+    changed.set_frame_file(path.clone());
+    changed.start().unwrap();
+    assert_eq!(changed.backend().refreshes(), 1);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn refuses_a_corrupt_radius() {
+    let path = state_path("corrupt");
+    for radius in &[0, -5, 500] {
+        let contents = format!("x = 50\ny = 60\nheading = 90\nradius = {}\n", radius);
+        fs::write(&path, contents).unwrap();
+        let mut arrow = Arrow::new(10);
+        let state = ArrowState::load(&path).unwrap().unwrap();
+        assert!(state.restore(&mut arrow).is_err(), "radius {}", radius);
+        assert_eq!((arrow.x, arrow.y, arrow.radius), (10, 10, 10));
+        assert_eq!(arrow.heading, Heading::DOWN);
+    }
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn pulls_a_saved_position_back_onto_the_panel() {
+    let path = state_path("off-panel");
+    fs::write(&path, "x = 400\ny = -30\nheading = 90\nradius = 20\n").unwrap();
+    let mut arrow = Arrow::new(10);
+    arrow.edge_mode = EdgeMode::Bounce;
+    ArrowState::load(&path)
+        .unwrap()
+        .unwrap()
+        .restore(&mut arrow)
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!((arrow.x, arrow.y, arrow.radius), (156, 20, 20));
+    assert_eq!(arrow.heading, Heading::LEFT);
+}
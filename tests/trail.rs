@@ -0,0 +1,57 @@
+use eink_arrow::arrow::{Arrow, ArrowMessage, Heading};
+use eink_arrow::frame::Ink;
+use eink_arrow::trail::{Trail, TrailStyle};
+
+fn arrow_with_trail(length: usize) -> Arrow {
+    let mut arrow = Arrow::new(10);
+    arrow.trail = Trail::new(length, TrailStyle::Dots, Ink::Black);
+    arrow
+}
+
+fn positions(arrow: &Arrow) -> Vec<(i32, i32)> {
+    arrow.trail.poses().map(|pose| (pose.x, pose.y)).collect()
+}
+
+#[test]
+fn keeps_only_the_most_recent_positions() {
+    let mut arrow = arrow_with_trail(2);
+    for _ in 0..4 {
+        arrow.apply(ArrowMessage::MoveForward(20));
+    }
+    assert_eq!(positions(&arrow), vec![(10, 50), (10, 70)]);
+    assert_eq!((arrow.x, arrow.y), (10, 90));
+}
+
+#[test]
+fn remembers_turns_made_on_the_spot() {
+    let mut arrow = arrow_with_trail(4);
+    arrow.apply(ArrowMessage::MoveForward(20));
+    arrow.apply(ArrowMessage::RotateLeft);
+    arrow.apply(ArrowMessage::MoveForward(20));
+
+    let headings: Vec<Heading> = arrow.trail.poses().map(|pose| pose.heading).collect();
+    assert_eq!(headings, vec![Heading::DOWN, Heading::RIGHT]);
+}
+
+#[test]
+fn skips_moves_blocked_by_the_edge() {
+    let mut arrow = arrow_with_trail(4);
+    arrow.apply(ArrowMessage::Rotate);
+    arrow.apply(ArrowMessage::MoveForward(20));
+    assert_eq!(positions(&arrow), vec![]);
+}
+
+#[test]
+fn is_cleared_by_reset() {
+    let mut arrow = arrow_with_trail(4);
+    arrow.apply(ArrowMessage::MoveForward(20));
+    arrow.apply(ArrowMessage::Reset);
+    assert_eq!(positions(&arrow), vec![]);
+}
+
+#[test]
+fn is_off_by_default() {
+    let mut arrow = Arrow::new(10);
+    arrow.apply(ArrowMessage::MoveForward(20));
+    assert_eq!(positions(&arrow), vec![]);
+}